@@ -2,13 +2,32 @@
 //!
 //! Calculates the expected and empirical counts and probabilities of coin flips for a specified
 //! number of iterations and flips per iteration.
+//!
+//! # Features
+//!
+//! - `log`: emits progress and summary messages through the `log` crate while running.
+//! - `plotters`: provides `save_bar_chart` for rendering results to a PNG with the `plotters`
+//!   crate.
+//! - `plot-labels`: adds a caption and axis labels to the charts, drawn with a system font
+//!   through `plotters`' `ttf` feature.
+//! - `test-utils`: provides `assert_fair` for checking simulations in test suites.
 
-use rand;
+use rand::distributions::{Distribution, WeightedIndex};
+use rand::rngs::{SmallRng, StdRng};
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::error::Error;
 use std::fmt;
-use std::collections::BTreeMap;
+use std::io::{self, Write};
+use std::time::{Duration, Instant};
 
 /// Runs a coin flip simulation for a specified number of iterations and flips per iteration.
 ///
+/// The parameters aren't validated; use [`try_run`] to get a [`SimError`] for ones that can't
+/// produce a meaningful result.
+///
 /// # Examples
 /// ```
 /// use coin_flip_simulation;
@@ -21,252 +40,7416 @@ use std::collections::BTreeMap;
 /// assert!(result.results.get("HHH").is_some());
 /// ```
 pub fn run(flips_per_iteration: usize, iterations: usize) -> CoinFlipResult {
+    #[cfg(feature = "log")]
+    log::debug!("starting run: flips_per_iteration={flips_per_iteration}, iterations={iterations}");
+
     let outcomes = get_all_outcomes(flips_per_iteration);
-    let mut results: BTreeMap<String, usize> = outcomes
-        .into_iter()
-        .map(|outcome| (outcome, 0))
-        .collect();
+    let mut results: BTreeMap<String, usize> =
+        outcomes.into_iter().map(|outcome| (outcome, 0)).collect();
 
-    for _ in 0..iterations {
-        let mut flips = String::new();
-        for _ in 0..flips_per_iteration {
-            flips.push_str(&Coin::flip().to_string());
+    #[cfg(feature = "log")]
+    let progress_interval = (iterations / 10).max(1);
+
+    #[cfg_attr(not(feature = "log"), allow(unused_variables))]
+    for i in 0..iterations {
+        *results
+            .entry(flip_outcome(flips_per_iteration))
+            .or_insert(0) += 1;
+
+        #[cfg(feature = "log")]
+        if (i + 1) % progress_interval == 0 {
+            log::trace!("completed {}/{iterations} iterations", i + 1);
         }
-        *results.entry(flips).or_insert(0) += 1;
     }
 
-    let results = results
-        .into_iter()
-        .map(|(key, count)|{
-            (key, EmpiricalResult::new(count, iterations))
-        })
-        .collect();
-    
-    let expected = EmpiricalResult::expected(flips_per_iteration, iterations);
-    CoinFlipResult::new(iterations, expected, results)
+    #[cfg(feature = "log")]
+    log::debug!(
+        "finished run: {iterations} iterations, {} of {} outcomes observed",
+        results.values().filter(|&&count| count > 0).count(),
+        results.len(),
+    );
+
+    CoinFlipResult::from_raw_counts(flips_per_iteration, iterations, results)
 }
 
-/// Gets a vector of all possible outcomes as strings.
+/// Runs a coin flip simulation that records heads and tails with custom symbols.
+///
+/// The first symbol stands for heads and the second for tails, so
+/// `run_with_symbols(n, i, ['H', 'T'])` behaves just like `run(n, i)`.
 ///
 /// # Examples
 /// ```
 /// use coin_flip_simulation;
 ///
-/// let outcomes = coin_flip_simulation::get_all_outcomes(3);
+/// let result = coin_flip_simulation::run_with_symbols(2, 4000, ['1', '0']);
 ///
-/// assert_eq!(outcomes, vec!["HHH", "HHT", "HTH", "HTT", "THH", "THT", "TTH", "TTT"]);
+/// assert_eq!(result.symbols, vec!['1', '0']);
+/// assert_eq!(result.expected.count, 1000);
+/// assert!(result.results.get("10").is_some());
+/// assert!(result.results.get("HT").is_none());
 /// ```
-pub fn get_all_outcomes(flips_per_iteration: usize) -> Vec<String> {
-    let num_outcomes = get_num_outcomes(flips_per_iteration);
-    let mut results = Vec::with_capacity(num_outcomes);
-    
-    let base: usize = 2;
-    for i in 0..num_outcomes {
-        let mut outcome = String::with_capacity(flips_per_iteration);
-        for j in 1..=flips_per_iteration {
-            let d = num_outcomes / base.pow(j as u32);
-            outcome.push_str(
-                if (i / d) % base == 0 { "H" } else { "T" }
-            );
-        }
+pub fn run_with_symbols(
+    flips_per_iteration: usize,
+    iterations: usize,
+    symbols: [char; 2],
+) -> CoinFlipResult {
+    let mut results: BTreeMap<String, usize> =
+        get_all_outcomes_with_symbols(flips_per_iteration, symbols)
+            .into_iter()
+            .map(|outcome| (outcome, 0))
+            .collect();
 
-        results.push(outcome);
+    for _ in 0..iterations {
+        *results
+            .entry(flip_outcome_with_symbols(flips_per_iteration, symbols))
+            .or_insert(0) += 1;
     }
 
-    results
+    let mut result = CoinFlipResult::from_raw_counts(flips_per_iteration, iterations, results);
+    result.symbols = symbols.to_vec();
+    result
 }
 
-/// Gets the number of possible outcomes.
+/// Runs a coin flip simulation with a bias that changes across iterations.
+///
+/// `bias_fn` is called with each iteration's index and gives the probability of heads for every
+/// flip in that iteration. Returns [`SimError::InvalidBias`] if a bias isn't between 0 and 1.
 ///
+/// The expected result is left as the fair-coin baseline, since the true expected probability of
+/// each outcome depends on the whole schedule.
+///
+/// # Examples
 /// ```
 /// use coin_flip_simulation;
 ///
-/// let num_outcomes = coin_flip_simulation::get_num_outcomes(3);
+/// let result = coin_flip_simulation::run_with_bias_schedule(2, 100, |_| 1.0).unwrap();
 ///
-/// assert_eq!(num_outcomes, 8);
+/// assert_eq!(result.results["HH"].count, 100);
+/// assert!(coin_flip_simulation::run_with_bias_schedule(2, 100, |_| 1.5).is_err());
 /// ```
-pub fn get_num_outcomes(flips_per_iteration: usize) -> usize {
-    let base: usize = 2;
-    base.pow(flips_per_iteration as u32)
+pub fn run_with_bias_schedule(
+    flips_per_iteration: usize,
+    iterations: usize,
+    bias_fn: impl Fn(usize) -> f64,
+) -> Result<CoinFlipResult, SimError> {
+    let mut results: BTreeMap<String, usize> = get_all_outcomes(flips_per_iteration)
+        .into_iter()
+        .map(|outcome| (outcome, 0))
+        .collect();
+
+    let mut rng = rand::thread_rng();
+    for i in 0..iterations {
+        let p_heads = validate_bias(bias_fn(i))?;
+        let flips: String = (0..flips_per_iteration)
+            .map(|_| if rng.gen_bool(p_heads) { 'H' } else { 'T' })
+            .collect();
+        *results.entry(flips).or_insert(0) += 1;
+    }
+
+    Ok(CoinFlipResult::from_raw_counts(
+        flips_per_iteration,
+        iterations,
+        results,
+    ))
 }
 
-/// Represents the result of running the coin flip simulation.
+/// Runs a coin flip simulation where each flip depends on the previous one.
 ///
-/// Contains the emprical results, the number of iterations, and the expected result.
-pub struct CoinFlipResult {
-    pub iterations: usize,
-    pub expected: EmpiricalResult,
-    pub results: BTreeMap<String, EmpiricalResult>,
-}
+/// The first flip of each iteration is heads with probability `p_initial_heads`. After that, a
+/// heads is followed by heads with probability `p_hh` and a tails is followed by tails with
+/// probability `p_tt`. Returns [`SimError::InvalidBias`] if any probability isn't between 0 and
+/// 1.
+///
+/// The expected result is left as the fair-coin baseline rather than the chain's distribution.
+///
+/// # Examples
+/// ```
+/// use coin_flip_simulation;
+///
+/// let result = coin_flip_simulation::run_markov(3, 100, 0.0, 0.0, 1.0).unwrap();
+///
+/// assert_eq!(result.results["HTH"].count, 100);
+/// ```
+pub fn run_markov(
+    flips_per_iteration: usize,
+    iterations: usize,
+    p_hh: f64,
+    p_tt: f64,
+    p_initial_heads: f64,
+) -> Result<CoinFlipResult, SimError> {
+    let p_hh = validate_bias(p_hh)?;
+    let p_tt = validate_bias(p_tt)?;
+    let p_initial_heads = validate_bias(p_initial_heads)?;
 
-impl CoinFlipResult {
-    fn new(iterations: usize, expected: EmpiricalResult, results: BTreeMap<String, EmpiricalResult>) -> Self {
-        CoinFlipResult {
-            iterations,
-            expected,
-            results,
-        }
-    }
-    
-    /// Converts a CoinFlipResult to a json string.
-    pub fn to_json_string(&self) -> String {    
-        let indent = "    ";
-        let mut json = format!(
-            "{{\n{indent}iterations: {}\n{indent}expected: {:.5}\n{indent}actual: {{\n", 
-            self.iterations, 
-            self.expected,
-        );
+    let mut results: BTreeMap<String, usize> = get_all_outcomes(flips_per_iteration)
+        .into_iter()
+        .map(|outcome| (outcome, 0))
+        .collect();
 
-        for (k, v) in self.results.iter() {
-            let entry = format!(
-                "{indent}{indent}{k}: {v}\n"
-            );
-            json.push_str(&entry);
+    let mut rng = rand::thread_rng();
+    for _ in 0..iterations {
+        let mut flips = String::with_capacity(flips_per_iteration);
+        let mut heads = rng.gen_bool(p_initial_heads);
+        for i in 0..flips_per_iteration {
+            if i > 0 {
+                heads = if heads {
+                    rng.gen_bool(p_hh)
+                } else {
+                    !rng.gen_bool(p_tt)
+                };
+            }
+            flips.push(if heads { 'H' } else { 'T' });
         }
-    
-        let close = format!("{indent}}}\n}}");
-        json.push_str(&close);
-        json
+        *results.entry(flips).or_insert(0) += 1;
     }
+
+    Ok(CoinFlipResult::from_raw_counts(
+        flips_per_iteration,
+        iterations,
+        results,
+    ))
 }
 
-impl fmt::Display for CoinFlipResult {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}",  self.to_json_string())
+/// Checks that `p_heads` is a valid probability.
+fn validate_bias(p_heads: f64) -> Result<f64, SimError> {
+    if (0.0..=1.0).contains(&p_heads) {
+        Ok(p_heads)
+    } else {
+        Err(SimError::InvalidBias(p_heads))
     }
 }
 
-/// Represents an empirical result.
+/// Runs `iterations_per_count` iterations for each number of flips in `flip_counts`.
 ///
-/// Contains the raw count of an outcome and the observed probability.
-pub struct EmpiricalResult {
-    pub count: usize,
-    pub probability: f64,
-}
-
-impl EmpiricalResult {
-    fn new(count: usize, iterations: usize) -> Self {
-        let probability: f64 = (count as f64) / (iterations as f64);
+/// Outcomes of every length are keyed together in a single map, with probabilities relative to
+/// the total number of iterations. Since outcomes of different lengths aren't equally likely, the
+/// expected result is given separately for each number of flips.
+///
+/// # Examples
+/// ```
+/// use coin_flip_simulation;
+///
+/// let result = coin_flip_simulation::run_variable(&[1, 2], 4000);
+///
+/// assert_eq!(result.iterations, 8000);
+/// assert_eq!(result.results.len(), 6);
+/// assert_eq!(result.expected[&1].count, 2000);
+/// assert_eq!(result.expected[&2].count, 1000);
+/// ```
+pub fn run_variable(flip_counts: &[usize], iterations_per_count: usize) -> VariableLengthResult {
+    let iterations = flip_counts.len() * iterations_per_count;
+    let mut counts: BTreeMap<String, usize> = BTreeMap::new();
+    let mut expected_counts: BTreeMap<usize, usize> = BTreeMap::new();
 
-        EmpiricalResult {
-            count,
-            probability,
+    for &flips_per_iteration in flip_counts {
+        for outcome in get_all_outcomes(flips_per_iteration) {
+            counts.entry(outcome).or_insert(0);
         }
+        for _ in 0..iterations_per_count {
+            *counts.entry(flip_outcome(flips_per_iteration)).or_insert(0) += 1;
+        }
+
+        *expected_counts.entry(flips_per_iteration).or_insert(0) +=
+            iterations_per_count / get_num_outcomes(flips_per_iteration);
     }
 
-    /// Gets the expected result for a specified number of iterations and flips_per_iteration.
-    pub fn expected(flips_per_iteration: usize, iterations: usize) -> Self {
-        let num_outcomes = get_num_outcomes(flips_per_iteration);
-        let count = iterations / num_outcomes;
+    let results = counts
+        .into_iter()
+        .map(|(key, count)| (key, EmpiricalResult::new(count, iterations)))
+        .collect();
+    let expected = expected_counts
+        .into_iter()
+        .map(|(flips_per_iteration, count)| {
+            (flips_per_iteration, EmpiricalResult::new(count, iterations))
+        })
+        .collect();
 
-        EmpiricalResult::new(count, iterations)
+    VariableLengthResult {
+        iterations,
+        expected,
+        results,
     }
 }
 
-impl fmt::Display for EmpiricalResult {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{{count: {}, probability: {:.5}}}", self.count, self.probability)
+/// Runs a coin flip simulation that discards the first `burn_in` iterations.
+///
+/// The burn-in iterations are flipped but not recorded, so the result's `iterations` only counts
+/// the `iterations - burn_in` recorded iterations. If `burn_in` is at least `iterations`, nothing
+/// is recorded.
+///
+/// # Examples
+/// ```
+/// use coin_flip_simulation;
+///
+/// let result = coin_flip_simulation::run_with_burn_in(3, 9000, 1000);
+///
+/// assert_eq!(result.iterations, 8000);
+/// assert_eq!(result.expected.count, 1000);
+/// ```
+pub fn run_with_burn_in(
+    flips_per_iteration: usize,
+    iterations: usize,
+    burn_in: usize,
+) -> CoinFlipResult {
+    let burn_in = burn_in.min(iterations);
+    for _ in 0..burn_in {
+        flip_outcome(flips_per_iteration);
     }
-}
 
-#[derive(PartialEq)]
-enum Coin {
-    Heads,
-    Tails,
+    let mut accumulator = ResultAccumulator::new(flips_per_iteration);
+    accumulator.add_run(iterations - burn_in);
+    accumulator.finalize()
 }
 
-impl Coin {
-    fn flip() -> Self {
-        if rand::random() {
-            Coin::Heads
-        } else {
-            Coin::Tails
-        }
+/// Runs a coin flip simulation and panics if any outcome's empirical probability deviates from
+/// the expected probability by more than `tolerance`.
+///
+/// The simulation is random, so this can fail spuriously when `iterations` is small relative to
+/// the number of outcomes or `tolerance` is tight. Leave plenty of headroom to avoid flaky tests.
+///
+/// # Examples
+/// ```
+/// # #[cfg(feature = "test-utils")]
+/// coin_flip_simulation::assert_fair(3, 80000, 0.02);
+/// ```
+#[cfg(feature = "test-utils")]
+pub fn assert_fair(flips_per_iteration: usize, iterations: usize, tolerance: f64) {
+    let result = run(flips_per_iteration, iterations);
+
+    for comparison in result.comparison() {
+        assert!(
+            comparison.abs_error <= tolerance,
+            "outcome {} deviates from expected by {:.5} (observed {:.5}, expected {:.5}, \
+             tolerance {:.5}) over {iterations} iterations",
+            comparison.outcome,
+            comparison.abs_error,
+            comparison.observed,
+            comparison.expected,
+            tolerance,
+        );
     }
 }
 
-impl fmt::Display for Coin {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        use Coin::{Heads, Tails};
+/// Flips a single stream of `stream_len` coins and counts the overlapping occurrences of
+/// `pattern`.
+///
+/// The probability is the fraction of the `stream_len - pattern.len() + 1` windows of the stream
+/// that match the pattern. Returns a [`SimError`] if the pattern is empty, contains anything
+/// other than `H` or `T`, or is longer than the stream.
+///
+/// # Examples
+/// ```
+/// use coin_flip_simulation;
+///
+/// let mut rng = rand::thread_rng();
+/// let result = coin_flip_simulation::pattern_frequency_in_stream(100000, "HT", &mut rng).unwrap();
+///
+/// assert!((result.probability - 0.25).abs() < 0.01);
+/// ```
+pub fn pattern_frequency_in_stream<R: Rng>(
+    stream_len: usize,
+    pattern: &str,
+    rng: &mut R,
+) -> Result<EmpiricalResult, SimError> {
+    let pattern: Vec<char> = pattern.chars().collect();
+    validate_pattern(&pattern, stream_len)?;
 
-        match self {
-            Heads => write!(f, "H"),
-            Tails => write!(f, "T"),
+    let mut window: VecDeque<char> = VecDeque::with_capacity(pattern.len());
+    let mut count = 0;
+    for _ in 0..stream_len {
+        if window.len() == pattern.len() {
+            window.pop_front();
+        }
+        window.push_back(if Coin::flip_with(rng).to_bool() {
+            'H'
+        } else {
+            'T'
+        });
+
+        if window.iter().eq(pattern.iter()) {
+            count += 1;
         }
     }
+
+    Ok(EmpiricalResult::new(count, stream_len - pattern.len() + 1))
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+/// Checks that `pattern` is a non-empty sequence of heads and tails no longer than `max_len`.
+fn validate_pattern(pattern: &[char], max_len: usize) -> Result<(), SimError> {
+    if pattern.is_empty() || pattern.iter().any(|c| !['H', 'T'].contains(c)) {
+        return Err(SimError::InvalidOutcome(pattern.iter().collect()));
+    }
+    if pattern.len() > max_len {
+        return Err(SimError::PatternTooLong {
+            pattern_len: pattern.len(),
+            max_len,
+        });
+    }
 
-    #[test]
-    fn test_get_all_outcomes_4() {
-        use Coin::{Heads, Tails};
+    Ok(())
+}
 
-        let outcomes = get_all_outcomes(4);
+/// Flips a coin `flips_per_iteration` times and records the sequence as a string.
+fn flip_outcome(flips_per_iteration: usize) -> String {
+    flip_outcome_with_symbols(flips_per_iteration, ['H', 'T'])
+}
 
-        let mut expected = Vec::new();
-        for i in [Heads, Tails] {
-            for j in [Heads, Tails] {
-                for k in [Heads, Tails] {
-                    for l in [Heads, Tails] {
-                        expected.push(format!("{i}{j}{k}{l}"));
-                    }
-                }
+/// Flips a coin `flips_per_iteration` times with the given random number generator and records
+/// the sequence as a string.
+fn flip_outcome_with_rng<R: Rng>(flips_per_iteration: usize, rng: &mut R) -> String {
+    (0..flips_per_iteration)
+        .map(|_| {
+            if Coin::flip_with(rng).to_bool() {
+                'H'
+            } else {
+                'T'
             }
-        }
+        })
+        .collect()
+}
 
-        assert_eq!(outcomes, expected);
+/// Flips a coin `flips_per_iteration` times and records the sequence with the heads and tails
+/// symbols.
+fn flip_outcome_with_symbols(flips_per_iteration: usize, symbols: [char; 2]) -> String {
+    let mut flips = String::with_capacity(flips_per_iteration);
+    for _ in 0..flips_per_iteration {
+        flips.push(if Coin::flip().to_bool() {
+            symbols[0]
+        } else {
+            symbols[1]
+        });
     }
 
-    #[test]
-    fn test_get_all_outcomes_6() {
-        use Coin::{Heads, Tails};
+    flips
+}
 
-        let outcomes = get_all_outcomes(6);
+/// Runs a coin flip simulation, returning a [`SimError`] if the number of possible outcomes
+/// overflows a `usize`, if the result's estimated size exceeds [`MAX_RESULT_BYTES`], or if there
+/// are no iterations to run.
+///
+/// # Examples
+/// ```
+/// use coin_flip_simulation::{self, SimError};
+///
+/// assert_eq!(coin_flip_simulation::try_run(3, 8000).unwrap().iterations, 8000);
+/// assert_eq!(coin_flip_simulation::try_run(3, 0).err(), Some(SimError::ZeroIterations));
+/// ```
+pub fn try_run(flips_per_iteration: usize, iterations: usize) -> Result<CoinFlipResult, SimError> {
+    if flips_per_iteration >= usize::BITS as usize {
+        return Err(SimError::Overflow {
+            flips_per_iteration,
+        });
+    }
+    if estimate(flips_per_iteration, iterations).exceeds_limit {
+        return Err(SimError::TooLarge {
+            flips_per_iteration,
+        });
+    }
+    if iterations == 0 {
+        return Err(SimError::ZeroIterations);
+    }
 
-        let mut expected = Vec::new();
-        for i in [Heads, Tails] {
-            for j in [Heads, Tails] {
-                for k in [Heads, Tails] {
-                    for l in [Heads, Tails] {
-                        for m in [Heads, Tails] {
-                            for n in [Heads, Tails] {
-                                expected.push(format!("{i}{j}{k}{l}{m}{n}"));
-                            }
-                        }
-                    }
-                }
-            }
-        }
+    Ok(run(flips_per_iteration, iterations))
+}
 
-        assert_eq!(outcomes, expected);
-    }
+/// Runs a coin flip simulation with parameters of any integer type convertible to `usize`.
+///
+/// Returns a [`SimError`] rather than panicking if either parameter doesn't fit in a `usize` on
+/// the current target, if the number of possible outcomes overflows a `usize`, if the result would
+/// be too large as in [`try_run`], or if there are no iterations to run.
+///
+/// # Examples
+/// ```
+/// use coin_flip_simulation;
+///
+/// let result = coin_flip_simulation::run_from(3u32, 8000u64).unwrap();
+///
+/// assert_eq!(result.iterations, 8000);
+/// assert!(coin_flip_simulation::run_from(-1i32, 8000).is_err());
+/// ```
+pub fn run_from<F, I>(flips_per_iteration: F, iterations: I) -> Result<CoinFlipResult, SimError>
+where
+    F: TryInto<usize>,
+    I: TryInto<usize>,
+{
+    let flips_per_iteration = flips_per_iteration
+        .try_into()
+        .map_err(|_| SimError::OutOfRange)?;
+    let iterations = iterations.try_into().map_err(|_| SimError::OutOfRange)?;
 
-    #[test]
-    fn test_coin_display() {
-        let h = Coin::Heads;
-        let t = Coin::Tails;
+    try_run(flips_per_iteration, iterations)
+}
 
-        assert_eq!(
-            format!("{h}{t}"), 
-            "HT"
-        );
+/// Runs a simulation that draws from a fixed set of heads and tails without replacement.
+///
+/// Each iteration shuffles `heads` H tokens and `tails` T tokens and records the resulting
+/// sequence, so every outcome contains exactly `heads` H's. Since each arrangement is equally
+/// likely, the expected result is uniform over the `C(heads + tails, heads)` possible sequences
+/// rather than over all `2^n` binomial outcomes.
+///
+/// Only those `C(heads + tails, heads)` sequences are generated, so this stays practical when the
+/// full set of binomial outcomes isn't. Returns [`SimError::Overflow`] if their number overflows a
+/// `usize`, or [`SimError::TooLarge`] if they'd take more than [`MAX_RESULT_BYTES`] to store.
+///
+/// # Examples
+/// ```
+/// use coin_flip_simulation;
+///
+/// let result = coin_flip_simulation::run_without_replacement(2, 1, 3000).unwrap();
+///
+/// assert_eq!(result.iterations, 3000);
+/// assert_eq!(result.results.len(), 3);
+/// assert_eq!(result.expected.count, 1000);
+/// assert!(result.results.get("HHT").is_some());
+/// assert!(result.results.get("TTH").is_none());
+/// ```
+pub fn run_without_replacement(
+    heads: usize,
+    tails: usize,
+    iterations: usize,
+) -> Result<CoinFlipResult, SimError> {
+    let Some(flips_per_iteration) = heads.checked_add(tails) else {
+        return Err(SimError::Overflow {
+            flips_per_iteration: usize::MAX,
+        });
+    };
+    let Some(num_outcomes) = binomial_coefficient(flips_per_iteration, heads) else {
+        return Err(SimError::Overflow {
+            flips_per_iteration,
+        });
+    };
+    if result_bytes(num_outcomes, flips_per_iteration).is_none_or(|bytes| bytes > MAX_RESULT_BYTES)
+    {
+        return Err(SimError::TooLarge {
+            flips_per_iteration,
+        });
     }
 
-    #[test]
-    fn test_coin_to_string() {
-        let h = Coin::Heads;
+    let mut results: BTreeMap<String, usize> = outcomes_with_k_heads(flips_per_iteration, heads)
+        .into_iter()
+        .map(|outcome| (outcome, 0))
+        .collect();
 
-        assert_eq!(
-            h.to_string(),
-            "H"
-        );
+    let mut tokens: Vec<Coin> = Vec::with_capacity(flips_per_iteration);
+    tokens.extend((0..heads).map(|_| Coin::Heads));
+    tokens.extend((0..tails).map(|_| Coin::Tails));
+
+    let mut rng = rand::thread_rng();
+    for _ in 0..iterations {
+        tokens.shuffle(&mut rng);
+        let flips: String = tokens.iter().map(|coin| coin.to_string()).collect();
+        *results.entry(flips).or_insert(0) += 1;
     }
 
-    #[test]
-    fn test_coin_eq() {
+    let num_outcomes = results.len();
+    let results = results
+        .into_iter()
+        .map(|(key, count)| (key, EmpiricalResult::new(count, iterations)))
+        .collect();
+
+    let expected = EmpiricalResult::new(iterations / num_outcomes, iterations);
+    Ok(CoinFlipResult::new(
+        flips_per_iteration,
+        iterations,
+        expected,
+        num_outcomes,
+        results,
+    ))
+}
+
+/// Gets the number of ways to choose `k` of `n` items, or `None` if it overflows a `usize`.
+fn binomial_coefficient(n: usize, k: usize) -> Option<usize> {
+    if k > n {
+        return Some(0);
+    }
+
+    let k = k.min(n - k);
+    (0..k).try_fold(1usize, |c, i| {
+        // Each partial product is itself a binomial coefficient, so the division is exact.
+        let c = u128::try_from(c).ok()? * (n - i) as u128 / (i + 1) as u128;
+        usize::try_from(c).ok()
+    })
+}
+
+/// Runs a reproducible coin flip simulation from a seed.
+///
+/// The iterations are split into fixed-size blocks, each flipped with its own generator seeded
+/// from `seed` and the block's index, so the counts are identical to those of
+/// [`run_parallel_seeded`] with the same seed, regardless of its number of threads.
+///
+/// # Examples
+/// ```
+/// use coin_flip_simulation;
+///
+/// let first = coin_flip_simulation::run_seeded(3, 8000, 42);
+/// let second = coin_flip_simulation::run_seeded(3, 8000, 42);
+///
+/// assert_eq!(first.results["HHH"].count, second.results["HHH"].count);
+/// ```
+pub fn run_seeded(flips_per_iteration: usize, iterations: usize, seed: u64) -> CoinFlipResult {
+    let mut results: BTreeMap<String, usize> = get_all_outcomes(flips_per_iteration)
+        .into_iter()
+        .map(|outcome| (outcome, 0))
+        .collect();
+
+    for start in (0..iterations).step_by(SEEDED_BLOCK_SIZE) {
+        let block_iterations = SEEDED_BLOCK_SIZE.min(iterations - start);
+        let mut rng = StdRng::seed_from_u64(block_seed(seed, start / SEEDED_BLOCK_SIZE));
+
+        for _ in 0..block_iterations {
+            *results
+                .entry(flip_outcome_with_rng(flips_per_iteration, &mut rng))
+                .or_insert(0) += 1;
+        }
+    }
+
+    CoinFlipResult::from_raw_counts(flips_per_iteration, iterations, results)
+}
+
+/// Runs a reproducible coin flip simulation from a seed across `threads` threads.
+///
+/// Each thread flips a share of the same seeded blocks as [`run_seeded`] and the per-thread counts
+/// are summed, so the result is bit-identical to the sequential run for the same seed. A `threads`
+/// of 0 is treated as 1.
+///
+/// # Examples
+/// ```
+/// use coin_flip_simulation;
+///
+/// let sequential = coin_flip_simulation::run_seeded(3, 8000, 7);
+/// let parallel = coin_flip_simulation::run_parallel_seeded(3, 8000, 7, 4);
+///
+/// assert_eq!(sequential.results["HTH"].count, parallel.results["HTH"].count);
+/// ```
+pub fn run_parallel_seeded(
+    flips_per_iteration: usize,
+    iterations: usize,
+    seed: u64,
+    threads: usize,
+) -> CoinFlipResult {
+    let threads = threads.max(1);
+    let num_blocks = iterations.div_ceil(SEEDED_BLOCK_SIZE);
+
+    let partial_counts: Vec<BTreeMap<String, usize>> = std::thread::scope(|scope| {
+        let handles: Vec<_> = (0..threads)
+            .map(|thread| {
+                scope.spawn(move || {
+                    let mut counts = BTreeMap::new();
+                    for block in (thread..num_blocks).step_by(threads) {
+                        let start = block * SEEDED_BLOCK_SIZE;
+                        let block_iterations = SEEDED_BLOCK_SIZE.min(iterations - start);
+                        let mut rng = StdRng::seed_from_u64(block_seed(seed, block));
+
+                        for _ in 0..block_iterations {
+                            *counts
+                                .entry(flip_outcome_with_rng(flips_per_iteration, &mut rng))
+                                .or_insert(0) += 1;
+                        }
+                    }
+
+                    counts
+                })
+            })
+            .collect();
+
+        handles
+            .into_iter()
+            .map(|handle| handle.join().unwrap())
+            .collect()
+    });
+
+    let mut results: BTreeMap<String, usize> = get_all_outcomes(flips_per_iteration)
+        .into_iter()
+        .map(|outcome| (outcome, 0))
+        .collect();
+    for counts in partial_counts {
+        for (outcome, count) in counts {
+            *results.entry(outcome).or_insert(0) += count;
+        }
+    }
+
+    CoinFlipResult::from_raw_counts(flips_per_iteration, iterations, results)
+}
+
+/// The number of iterations flipped with each seeded generator.
+const SEEDED_BLOCK_SIZE: usize = 4096;
+
+/// Derives the seed of a block from the master seed with a SplitMix64 step.
+fn block_seed(seed: u64, block: usize) -> u64 {
+    let mut z = seed.wrapping_add((block as u64 + 1).wrapping_mul(0x9E37_79B9_7F4A_7C15));
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+/// Runs as many iterations as fit in a budget of `total_flips` flips.
+///
+/// Returns the result of the `total_flips / flips_per_iteration` iterations along with the number
+/// of leftover flips that didn't fill a whole iteration.
+///
+/// # Examples
+/// ```
+/// use coin_flip_simulation;
+///
+/// let (result, leftover) = coin_flip_simulation::run_with_flip_budget(3, 1000);
+///
+/// assert_eq!(result.iterations, 333);
+/// assert_eq!(leftover, 1);
+/// ```
+pub fn run_with_flip_budget(
+    flips_per_iteration: usize,
+    total_flips: usize,
+) -> (CoinFlipResult, usize) {
+    let iterations = total_flips.checked_div(flips_per_iteration).unwrap_or(0);
+    let leftover = total_flips - iterations * flips_per_iteration;
+
+    (run(flips_per_iteration, iterations), leftover)
+}
+
+/// Runs a coin flip simulation that calls `sink` with a snapshot of the results every `every`
+/// iterations.
+///
+/// Each snapshot's counts sum to the number of iterations completed so far, and its probabilities
+/// are relative to that number. An `every` of 0 never calls `sink`.
+///
+/// # Examples
+/// ```
+/// use coin_flip_simulation;
+///
+/// let mut snapshots = 0;
+/// let result = coin_flip_simulation::run_with_sampler(3, 1000, 100, |_| snapshots += 1);
+///
+/// assert_eq!(snapshots, 10);
+/// assert_eq!(result.iterations, 1000);
+/// ```
+pub fn run_with_sampler(
+    flips_per_iteration: usize,
+    iterations: usize,
+    every: usize,
+    mut sink: impl FnMut(&BTreeMap<String, EmpiricalResult>),
+) -> CoinFlipResult {
+    let mut results: BTreeMap<String, usize> = get_all_outcomes(flips_per_iteration)
+        .into_iter()
+        .map(|outcome| (outcome, 0))
+        .collect();
+
+    for i in 1..=iterations {
+        *results
+            .entry(flip_outcome(flips_per_iteration))
+            .or_insert(0) += 1;
+
+        if every > 0 && i % every == 0 {
+            let snapshot = results
+                .iter()
+                .map(|(key, &count)| (key.clone(), EmpiricalResult::new(count, i)))
+                .collect();
+            sink(&snapshot);
+        }
+    }
+
+    CoinFlipResult::from_raw_counts(flips_per_iteration, iterations, results)
+}
+
+/// Runs a coin flip simulation that records each outcome as a bitmask instead of a string, so
+/// the loop doesn't allocate.
+///
+/// Bit `i` of a key is set when flip `i` came up heads; use [`bitmask_to_string`] to turn a key
+/// into the outcome string [`run`] would have recorded. Outcomes that never occurred are absent
+/// from the map.
+///
+/// # Panics
+///
+/// Panics if `flips_per_iteration` is greater than 64.
+///
+/// # Examples
+/// ```
+/// use coin_flip_simulation;
+///
+/// let counts = coin_flip_simulation::run_bitmask(3, 8000);
+///
+/// assert!(counts.keys().all(|&mask| mask < 8));
+/// assert_eq!(counts.values().sum::<usize>(), 8000);
+/// ```
+pub fn run_bitmask(flips_per_iteration: usize, iterations: usize) -> HashMap<u64, usize> {
+    assert!(
+        flips_per_iteration <= u64::BITS as usize,
+        "run_bitmask supports at most 64 flips per iteration"
+    );
+
+    let mask = u64::MAX
+        .checked_shr((u64::BITS as usize - flips_per_iteration) as u32)
+        .unwrap_or(0);
+    let mut rng = rand::thread_rng();
+    let mut results = HashMap::new();
+
+    for _ in 0..iterations {
+        *results.entry(rng.gen::<u64>() & mask).or_insert(0) += 1;
+    }
+
+    results
+}
+
+/// Converts a bitmask key from [`run_bitmask`] to its outcome string, reading flip `i` from bit
+/// `i`.
+///
+/// # Examples
+/// ```
+/// use coin_flip_simulation;
+///
+/// assert_eq!(coin_flip_simulation::bitmask_to_string(0b011, 3), "HHT");
+/// ```
+pub fn bitmask_to_string(mask: u64, flips_per_iteration: usize) -> String {
+    (0..flips_per_iteration)
+        .map(|i| if mask >> i & 1 == 1 { 'H' } else { 'T' })
+        .collect()
+}
+
+/// Repeatedly flips a coin until `pattern` first appears and summarizes the number of flips it
+/// took over `trials` trials.
+///
+/// The median and 95th percentile are estimated with the P² algorithm, so memory use stays
+/// constant however many trials are run. Returns a [`SimError`] if the pattern is empty or
+/// contains anything other than `H` or `T`, or if `trials` is 0.
+///
+/// # Examples
+/// ```
+/// use coin_flip_simulation;
+///
+/// let mut rng = rand::thread_rng();
+/// let summary = coin_flip_simulation::run_waiting_time_summary("HH", 20000, &mut rng).unwrap();
+///
+/// assert!((summary.mean - 6.0).abs() < 0.3);
+/// assert!(summary.median <= summary.p95);
+/// ```
+pub fn run_waiting_time_summary<R: Rng>(
+    pattern: &str,
+    trials: usize,
+    rng: &mut R,
+) -> Result<WaitingTimeSummary, SimError> {
+    let pattern: Vec<char> = pattern.chars().collect();
+    validate_pattern(&pattern, usize::MAX)?;
+    if trials == 0 {
+        return Err(SimError::ZeroIterations);
+    }
+
+    let mut total = 0.0;
+    let mut median = P2Quantile::new(0.5);
+    let mut p95 = P2Quantile::new(0.95);
+    for _ in 0..trials {
+        let flips = flips_until(&pattern, rng) as f64;
+
+        total += flips;
+        median.observe(flips);
+        p95.observe(flips);
+    }
+
+    Ok(WaitingTimeSummary {
+        trials,
+        mean: total / trials as f64,
+        median: median.estimate(),
+        p95: p95.estimate(),
+    })
+}
+
+/// Flips a coin until the last flips match `pattern` and returns the number of flips.
+fn flips_until<R: Rng>(pattern: &[char], rng: &mut R) -> usize {
+    let mut window: VecDeque<char> = VecDeque::with_capacity(pattern.len());
+    let mut flips = 0;
+    loop {
+        if window.len() == pattern.len() {
+            window.pop_front();
+        }
+        window.push_back(if Coin::flip_with(rng).to_bool() {
+            'H'
+        } else {
+            'T'
+        });
+        flips += 1;
+
+        if window.iter().eq(pattern.iter()) {
+            return flips;
+        }
+    }
+}
+
+/// Estimates a quantile of a stream of observations in constant memory with the P² algorithm of
+/// Jain and Chlamtac.
+struct P2Quantile {
+    quantile: f64,
+    count: usize,
+    heights: [f64; 5],
+    positions: [f64; 5],
+    desired: [f64; 5],
+    increments: [f64; 5],
+}
+
+impl P2Quantile {
+    fn new(quantile: f64) -> Self {
+        Self {
+            quantile,
+            count: 0,
+            heights: [0.0; 5],
+            positions: [1.0, 2.0, 3.0, 4.0, 5.0],
+            desired: [
+                1.0,
+                1.0 + 2.0 * quantile,
+                1.0 + 4.0 * quantile,
+                3.0 + 2.0 * quantile,
+                5.0,
+            ],
+            increments: [0.0, quantile / 2.0, quantile, (1.0 + quantile) / 2.0, 1.0],
+        }
+    }
+
+    fn observe(&mut self, x: f64) {
+        if self.count < 5 {
+            self.heights[self.count] = x;
+            self.count += 1;
+            if self.count == 5 {
+                self.heights.sort_by(f64::total_cmp);
+            }
+            return;
+        }
+
+        let cell = if x < self.heights[0] {
+            self.heights[0] = x;
+            0
+        } else if x >= self.heights[4] {
+            self.heights[4] = x;
+            3
+        } else {
+            (0..4).rfind(|&i| self.heights[i] <= x).unwrap()
+        };
+
+        self.count += 1;
+        for i in cell + 1..5 {
+            self.positions[i] += 1.0;
+        }
+        for i in 0..5 {
+            self.desired[i] += self.increments[i];
+        }
+
+        for i in 1..4 {
+            let offset = self.desired[i] - self.positions[i];
+            let gap_above = self.positions[i + 1] - self.positions[i];
+            let gap_below = self.positions[i - 1] - self.positions[i];
+            if (offset >= 1.0 && gap_above > 1.0) || (offset <= -1.0 && gap_below < -1.0) {
+                let step = offset.signum();
+                let parabolic = self.parabolic(i, step);
+
+                self.heights[i] =
+                    if self.heights[i - 1] < parabolic && parabolic < self.heights[i + 1] {
+                        parabolic
+                    } else {
+                        let j = if step > 0.0 { i + 1 } else { i - 1 };
+                        self.heights[i]
+                            + step * (self.heights[j] - self.heights[i])
+                                / (self.positions[j] - self.positions[i])
+                    };
+                self.positions[i] += step;
+            }
+        }
+    }
+
+    /// Gets the piecewise-parabolic prediction for marker `i` moved by `step`.
+    fn parabolic(&self, i: usize, step: f64) -> f64 {
+        let (q, n) = (&self.heights, &self.positions);
+
+        q[i] + step / (n[i + 1] - n[i - 1])
+            * ((n[i] - n[i - 1] + step) * (q[i + 1] - q[i]) / (n[i + 1] - n[i])
+                + (n[i + 1] - n[i] - step) * (q[i] - q[i - 1]) / (n[i] - n[i - 1]))
+    }
+
+    fn estimate(&self) -> f64 {
+        if self.count >= 5 {
+            return self.heights[2];
+        }
+
+        let mut seen = self.heights[..self.count].to_vec();
+        seen.sort_by(f64::total_cmp);
+        seen.get((self.quantile * (self.count as f64 - 1.0)).round() as usize)
+            .copied()
+            .unwrap_or(f64::NAN)
+    }
+}
+
+/// Runs a coin flip simulation with the kind of random number generator given by `kind`.
+///
+/// # Examples
+/// ```
+/// use coin_flip_simulation::{self, RngKind};
+///
+/// let result = coin_flip_simulation::run_with_kind(3, 8000, RngKind::Fast);
+///
+/// assert_eq!(result.iterations, 8000);
+/// ```
+pub fn run_with_kind(
+    flips_per_iteration: usize,
+    iterations: usize,
+    kind: RngKind,
+) -> CoinFlipResult {
+    match kind {
+        RngKind::Fast => run_with_rng(
+            flips_per_iteration,
+            iterations,
+            &mut SmallRng::from_entropy(),
+        ),
+        RngKind::Crypto => run_with_rng(
+            flips_per_iteration,
+            iterations,
+            &mut ChaCha20Rng::from_entropy(),
+        ),
+    }
+}
+
+/// Runs a coin flip simulation with the given random number generator.
+fn run_with_rng<R: Rng>(
+    flips_per_iteration: usize,
+    iterations: usize,
+    rng: &mut R,
+) -> CoinFlipResult {
+    let mut results: BTreeMap<String, usize> = get_all_outcomes(flips_per_iteration)
+        .into_iter()
+        .map(|outcome| (outcome, 0))
+        .collect();
+
+    for _ in 0..iterations {
+        *results
+            .entry(flip_outcome_with_rng(flips_per_iteration, rng))
+            .or_insert(0) += 1;
+    }
+
+    CoinFlipResult::from_raw_counts(flips_per_iteration, iterations, results)
+}
+
+/// Runs a coin flip simulation that also records the index of the iteration in which each
+/// outcome first occurred, or `None` if it never did.
+///
+/// # Examples
+/// ```
+/// use coin_flip_simulation;
+///
+/// let (result, first_seen) = coin_flip_simulation::run_with_first_seen(2, 1000);
+///
+/// assert_eq!(first_seen.len(), result.results.len());
+/// assert!(first_seen.values().any(|&index| index == Some(0)));
+/// ```
+pub fn run_with_first_seen(
+    flips_per_iteration: usize,
+    iterations: usize,
+) -> (CoinFlipResult, BTreeMap<String, Option<usize>>) {
+    let mut results: BTreeMap<String, usize> = BTreeMap::new();
+    let mut first_seen: BTreeMap<String, Option<usize>> = BTreeMap::new();
+    for outcome in get_all_outcomes(flips_per_iteration) {
+        results.insert(outcome.clone(), 0);
+        first_seen.insert(outcome, None);
+    }
+
+    for i in 0..iterations {
+        let outcome = flip_outcome(flips_per_iteration);
+        first_seen.get_mut(&outcome).unwrap().get_or_insert(i);
+        *results.entry(outcome).or_insert(0) += 1;
+    }
+
+    (
+        CoinFlipResult::from_raw_counts(flips_per_iteration, iterations, results),
+        first_seen,
+    )
+}
+
+/// Plays `iterations` best-of-`flips_per_iteration` games and gets the empirical probability that
+/// heads wins the majority of the flips.
+///
+/// Ties, which can only happen with an even number of flips, are decided by `tie_rule`.
+///
+/// # Examples
+/// ```
+/// use coin_flip_simulation::{self, TieRule};
+///
+/// let result = coin_flip_simulation::run_majority(3, 10000, TieRule::TailsWins);
+///
+/// assert!((result.probability - 0.5).abs() < 0.05);
+/// ```
+pub fn run_majority(
+    flips_per_iteration: usize,
+    iterations: usize,
+    tie_rule: TieRule,
+) -> EmpiricalResult {
+    let mut heads_wins = 0;
+    for _ in 0..iterations {
+        let heads = (0..flips_per_iteration)
+            .filter(|_| Coin::flip().to_bool())
+            .count();
+        let tails = flips_per_iteration - heads;
+
+        if heads > tails || (heads == tails && tie_rule == TieRule::HeadsWins) {
+            heads_wins += 1;
+        }
+    }
+
+    EmpiricalResult::new(heads_wins, iterations)
+}
+
+/// Runs iterations until `target_outcome` has occurred `target_count` times and returns the result,
+/// whose `iterations` is the number of iterations that took.
+///
+/// Returns a [`SimError`] if `target_outcome` isn't `flips_per_iteration` heads and tails.
+///
+/// # Examples
+/// ```
+/// use coin_flip_simulation;
+///
+/// let mut rng = rand::thread_rng();
+/// let result = coin_flip_simulation::run_until_count(2, "HT", 10, &mut rng).unwrap();
+///
+/// assert_eq!(result.results["HT"].count, 10);
+/// assert!(result.iterations >= 10);
+/// ```
+pub fn run_until_count<R: Rng>(
+    flips_per_iteration: usize,
+    target_outcome: &str,
+    target_count: usize,
+    rng: &mut R,
+) -> Result<CoinFlipResult, SimError> {
+    let is_valid = target_outcome.chars().count() == flips_per_iteration
+        && target_outcome.chars().all(|c| c == 'H' || c == 'T');
+    if !is_valid {
+        return Err(SimError::InvalidOutcome(target_outcome.to_string()));
+    }
+
+    let mut results: BTreeMap<String, usize> = get_all_outcomes(flips_per_iteration)
+        .into_iter()
+        .map(|outcome| (outcome, 0))
+        .collect();
+
+    let mut iterations = 0;
+    while results[target_outcome] < target_count {
+        *results
+            .entry(flip_outcome_with_rng(flips_per_iteration, rng))
+            .or_insert(0) += 1;
+        iterations += 1;
+    }
+
+    Ok(CoinFlipResult::from_raw_counts(
+        flips_per_iteration,
+        iterations,
+        results,
+    ))
+}
+
+/// Runs a coin flip simulation with antithetic variates, pairing each random outcome with its
+/// complement.
+///
+/// Every other iteration flips the heads and tails of the one before it, so the pair's errors
+/// cancel out and estimates of symmetric quantities, such as the mean number of heads, have less
+/// variance than with independent iterations. Totals over both halves are exact by construction:
+/// exactly half of all flips are heads. Returns a [`SimError`] if `iterations` is odd.
+///
+/// # Examples
+/// ```
+/// use coin_flip_simulation;
+///
+/// let mut rng = rand::thread_rng();
+/// let result = coin_flip_simulation::run_antithetic(3, 8000, &mut rng).unwrap();
+///
+/// assert_eq!(result.results["HHH"].count, result.results["TTT"].count);
+/// ```
+pub fn run_antithetic<R: Rng>(
+    flips_per_iteration: usize,
+    iterations: usize,
+    rng: &mut R,
+) -> Result<CoinFlipResult, SimError> {
+    if !iterations.is_multiple_of(2) {
+        return Err(SimError::OddIterations(iterations));
+    }
+
+    let mut results: BTreeMap<String, usize> = get_all_outcomes(flips_per_iteration)
+        .into_iter()
+        .map(|outcome| (outcome, 0))
+        .collect();
+
+    for _ in 0..iterations / 2 {
+        let outcome = flip_outcome_with_rng(flips_per_iteration, rng);
+        let complement: String = outcome
+            .chars()
+            .map(|c| if c == 'H' { 'T' } else { 'H' })
+            .collect();
+
+        *results.entry(outcome).or_insert(0) += 1;
+        *results.entry(complement).or_insert(0) += 1;
+    }
+
+    Ok(CoinFlipResult::from_raw_counts(
+        flips_per_iteration,
+        iterations,
+        results,
+    ))
+}
+
+/// Runs `iterations` random walks of `flips_per_iteration` steps, stepping up for heads and down
+/// for tails.
+///
+/// Each walk is the position after each flip, so its last value is the number of heads minus the
+/// number of tails.
+///
+/// # Examples
+/// ```
+/// use coin_flip_simulation;
+///
+/// let walks = coin_flip_simulation::run_walk(10, 5);
+///
+/// assert_eq!(walks.len(), 5);
+/// assert!(walks.iter().all(|walk| walk.len() == 10));
+/// ```
+pub fn run_walk(flips_per_iteration: usize, iterations: usize) -> Vec<Vec<i64>> {
+    (0..iterations)
+        .map(|_| {
+            (0..flips_per_iteration)
+                .scan(0, |position, _| {
+                    *position += if Coin::flip().to_bool() { 1 } else { -1 };
+                    Some(*position)
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Gets the mean number of times a walk of `flips_per_iteration` steps, as in [`run_walk`],
+/// changes sign over `iterations` walks.
+///
+/// A sign change is the walk passing from above zero to below it or back again, possibly by way
+/// of zero, so touching zero and returning to the same side doesn't count. By the arcsine law,
+/// walks change sign far less often than intuition suggests. Returns 0 for zero iterations.
+///
+/// # Examples
+/// ```
+/// use coin_flip_simulation;
+///
+/// let mut rng = rand::thread_rng();
+/// let crossings = coin_flip_simulation::mean_zero_crossings(100, 1000, &mut rng);
+///
+/// assert!(crossings > 0.0 && crossings < 20.0);
+/// ```
+pub fn mean_zero_crossings<R: Rng>(
+    flips_per_iteration: usize,
+    iterations: usize,
+    rng: &mut R,
+) -> f64 {
+    if iterations == 0 {
+        return 0.0;
+    }
+
+    let total: usize = (0..iterations)
+        .map(|_| zero_crossings((0..flips_per_iteration).map(|_| rng.gen_bool(0.5))))
+        .sum();
+
+    total as f64 / iterations as f64
+}
+
+/// Counts the sign changes of the walk that steps up for each `true` flip and down for each
+/// `false` one.
+fn zero_crossings(flips: impl Iterator<Item = bool>) -> usize {
+    let mut position = 0i64;
+    let mut last_sign = 0;
+    let mut crossings = 0;
+
+    for heads in flips {
+        position += if heads { 1 } else { -1 };
+
+        let sign = position.signum();
+        if sign != 0 {
+            if last_sign != 0 && sign != last_sign {
+                crossings += 1;
+            }
+            last_sign = sign;
+        }
+    }
+
+    crossings
+}
+
+/// Runs a coin flip simulation with a coin that lands on its edge with probability `p_edge`,
+/// recorded as `E`, and otherwise lands heads or tails with equal probability.
+///
+/// The result covers all `3^flips_per_iteration` outcomes and its symbols are `H`, `T` and `E`.
+/// Since the outcomes aren't equally likely, the expected probability of each is given by
+/// [`CoinFlipResult::iter_with_expected`]. `expected` is still the uniform
+/// `iterations / 3^flips_per_iteration`, as if every outcome were equally likely. Returns a
+/// [`SimError`] if `p_edge` isn't between 0 and 1, or if the outcomes overflow a `usize` or
+/// would take more than [`MAX_RESULT_BYTES`].
+///
+/// # Examples
+/// ```
+/// use coin_flip_simulation;
+///
+/// let result = coin_flip_simulation::run_with_edge(2, 1000, 0.1).unwrap();
+///
+/// assert_eq!(result.results.len(), 9);
+/// assert_eq!(result.symbols, vec!['H', 'T', 'E']);
+/// ```
+pub fn run_with_edge(
+    flips_per_iteration: usize,
+    iterations: usize,
+    p_edge: f64,
+) -> Result<CoinFlipResult, SimError> {
+    let p_edge = validate_bias(p_edge)?;
+    let symbols = ['H', 'T', 'E'];
+    let Some(num_outcomes) = u32::try_from(flips_per_iteration)
+        .ok()
+        .and_then(|flips| symbols.len().checked_pow(flips))
+    else {
+        return Err(SimError::Overflow {
+            flips_per_iteration,
+        });
+    };
+    if result_bytes(num_outcomes, flips_per_iteration).is_none_or(|bytes| bytes > MAX_RESULT_BYTES)
+    {
+        return Err(SimError::TooLarge {
+            flips_per_iteration,
+        });
+    }
+    let outcomes = outcomes_over_symbols(flips_per_iteration, &symbols);
+
+    let mut rng = rand::thread_rng();
+    let mut counts: BTreeMap<String, usize> = outcomes
+        .iter()
+        .map(|outcome| (outcome.clone(), 0))
+        .collect();
+    for _ in 0..iterations {
+        let outcome: String = (0..flips_per_iteration)
+            .map(|_| {
+                if rng.gen_bool(p_edge) {
+                    'E'
+                } else if Coin::flip_with(&mut rng).to_bool() {
+                    'H'
+                } else {
+                    'T'
+                }
+            })
+            .collect();
+        *counts.entry(outcome).or_insert(0) += 1;
+    }
+
+    let p_side = (1.0 - p_edge) / 2.0;
+    let expected_by_outcome = outcomes
+        .into_iter()
+        .map(|outcome| {
+            let edges = outcome.matches('E').count();
+            let sides = flips_per_iteration - edges;
+            (
+                outcome,
+                p_edge.powi(edges as i32) * p_side.powi(sides as i32),
+            )
+        })
+        .collect();
+
+    let results = counts
+        .into_iter()
+        .map(|(outcome, count)| (outcome, EmpiricalResult::new(count, iterations)))
+        .collect();
+    let expected = EmpiricalResult::new(iterations / num_outcomes, iterations);
+
+    let mut result = CoinFlipResult::new(
+        flips_per_iteration,
+        iterations,
+        expected,
+        num_outcomes,
+        results,
+    );
+    result.symbols = symbols.to_vec();
+    result.expected_by_outcome = Some(expected_by_outcome);
+    Ok(result)
+}
+
+/// Runs a coin flip simulation that flips `flips_per_iteration` coins per iteration but only
+/// records the flips at `record_positions`, in that order.
+///
+/// The result is over the `2^record_positions.len()` projected outcomes, so its
+/// `flips_per_iteration` is the number of recorded positions and its expected result is the
+/// marginal distribution of those flips. Returns a [`SimError`] if a position is repeated or
+/// isn't less than `flips_per_iteration`.
+///
+/// # Examples
+/// ```
+/// use coin_flip_simulation;
+///
+/// let result = coin_flip_simulation::run_projected(5, &[0, 4], 1000).unwrap();
+///
+/// assert_eq!(result.flips_per_iteration, 2);
+/// assert_eq!(result.results.len(), 4);
+/// ```
+pub fn run_projected(
+    flips_per_iteration: usize,
+    record_positions: &[usize],
+    iterations: usize,
+) -> Result<CoinFlipResult, SimError> {
+    for (i, &position) in record_positions.iter().enumerate() {
+        if position >= flips_per_iteration || record_positions[..i].contains(&position) {
+            return Err(SimError::InvalidPosition {
+                position,
+                flips_per_iteration,
+            });
+        }
+    }
+
+    let mut results: BTreeMap<String, usize> = get_all_outcomes(record_positions.len())
+        .into_iter()
+        .map(|outcome| (outcome, 0))
+        .collect();
+
+    for _ in 0..iterations {
+        let flips: Vec<char> = flip_outcome(flips_per_iteration).chars().collect();
+        let projected: String = record_positions
+            .iter()
+            .map(|&position| flips[position])
+            .collect();
+        *results.entry(projected).or_insert(0) += 1;
+    }
+
+    Ok(CoinFlipResult::from_raw_counts(
+        record_positions.len(),
+        iterations,
+        results,
+    ))
+}
+
+/// Runs `iterations` iterations of `flips_per_iteration` flips and gets the empirical probability
+/// that patterns `a` and `b` both occur in the same iteration, along with the probability of
+/// each.
+///
+/// Comparing `joint` with `marginal_a * marginal_b` shows whether the patterns occur
+/// independently. Returns a [`SimError`] if either pattern is empty, contains anything other than
+/// `H` or `T`, or is longer than `flips_per_iteration`, or if there are no iterations to run.
+///
+/// # Examples
+/// ```
+/// use coin_flip_simulation;
+///
+/// let mut rng = rand::thread_rng();
+/// let p = coin_flip_simulation::joint_pattern_probability(10, 1000, "HH", "TT", &mut rng);
+/// let p = p.unwrap();
+///
+/// assert!(p.joint <= p.marginal_a.min(p.marginal_b));
+/// ```
+pub fn joint_pattern_probability<R: Rng>(
+    flips_per_iteration: usize,
+    iterations: usize,
+    a: &str,
+    b: &str,
+    rng: &mut R,
+) -> Result<JointPatternProbability, SimError> {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    validate_pattern(&a, flips_per_iteration)?;
+    validate_pattern(&b, flips_per_iteration)?;
+    if iterations == 0 {
+        return Err(SimError::ZeroIterations);
+    }
+
+    let (mut joint, mut count_a, mut count_b) = (0, 0, 0);
+    for _ in 0..iterations {
+        let flips: Vec<char> = flip_outcome_with_rng(flips_per_iteration, rng)
+            .chars()
+            .collect();
+        let has_a = flips.windows(a.len()).any(|window| window == a);
+        let has_b = flips.windows(b.len()).any(|window| window == b);
+
+        count_a += has_a as usize;
+        count_b += has_b as usize;
+        joint += (has_a && has_b) as usize;
+    }
+
+    let iterations = iterations as f64;
+    Ok(JointPatternProbability {
+        joint: joint as f64 / iterations,
+        marginal_a: count_a as f64 / iterations,
+        marginal_b: count_b as f64 / iterations,
+    })
+}
+
+/// Runs a coin flip simulation that also keeps a uniformly random sample of `sample_size` of the
+/// flipped sequences, in memory proportional to `sample_size` rather than `iterations`.
+///
+/// The sample is chosen by reservoir sampling: the first `sample_size` sequences fill the sample,
+/// and each later sequence `i` replaces a random one with probability `sample_size / (i + 1)`.
+/// With fewer iterations than `sample_size`, every sequence is kept.
+///
+/// # Examples
+/// ```
+/// use coin_flip_simulation;
+///
+/// let mut rng = rand::thread_rng();
+/// let (result, sample) = coin_flip_simulation::run_with_reservoir(3, 8000, 100, &mut rng);
+///
+/// assert_eq!(sample.len(), 100);
+/// assert!(sample.iter().all(|outcome| result.results.contains_key(outcome)));
+/// ```
+pub fn run_with_reservoir<R: Rng>(
+    flips_per_iteration: usize,
+    iterations: usize,
+    sample_size: usize,
+    rng: &mut R,
+) -> (CoinFlipResult, Vec<String>) {
+    let mut results: BTreeMap<String, usize> = get_all_outcomes(flips_per_iteration)
+        .into_iter()
+        .map(|outcome| (outcome, 0))
+        .collect();
+
+    let mut reservoir = Vec::with_capacity(sample_size.min(iterations));
+    for i in 0..iterations {
+        let outcome = flip_outcome_with_rng(flips_per_iteration, rng);
+        *results.entry(outcome.clone()).or_insert(0) += 1;
+
+        if i < sample_size {
+            reservoir.push(outcome);
+        } else {
+            let j = rng.gen_range(0..=i);
+            if j < sample_size {
+                reservoir[j] = outcome;
+            }
+        }
+    }
+
+    (
+        CoinFlipResult::from_raw_counts(flips_per_iteration, iterations, results),
+        reservoir,
+    )
+}
+
+/// Runs a coin flip simulation with a separate coin for each position, flipping coin `i` once per
+/// iteration with probability `biases[i]` of heads.
+///
+/// The expected probability of each outcome is the product of its coins' probabilities, and is
+/// given by [`CoinFlipResult::iter_with_expected`]. Returns a [`SimError`] if any bias isn't
+/// between 0 and 1.
+///
+/// # Examples
+/// ```
+/// use coin_flip_simulation;
+///
+/// let result = coin_flip_simulation::run_multi_coin(&[0.5, 0.9], 1000).unwrap();
+///
+/// assert_eq!(result.flips_per_iteration, 2);
+/// assert!(result.results["HH"].count > result.results["HT"].count);
+/// ```
+pub fn run_multi_coin(biases: &[f64], iterations: usize) -> Result<CoinFlipResult, SimError> {
+    let biases: Vec<f64> = biases
+        .iter()
+        .map(|&p_heads| validate_bias(p_heads))
+        .collect::<Result<_, _>>()?;
+    let outcomes = get_all_outcomes(biases.len());
+
+    let mut rng = rand::thread_rng();
+    let mut counts: BTreeMap<String, usize> = outcomes
+        .iter()
+        .map(|outcome| (outcome.clone(), 0))
+        .collect();
+    for _ in 0..iterations {
+        let outcome: String = biases
+            .iter()
+            .map(|&p_heads| if rng.gen_bool(p_heads) { 'H' } else { 'T' })
+            .collect();
+        *counts.entry(outcome).or_insert(0) += 1;
+    }
+
+    let expected_by_outcome = outcomes
+        .into_iter()
+        .map(|outcome| {
+            let probability = outcome
+                .chars()
+                .zip(&biases)
+                .map(|(c, &p_heads)| if c == 'H' { p_heads } else { 1.0 - p_heads })
+                .product();
+            (outcome, probability)
+        })
+        .collect();
+
+    let mut result = CoinFlipResult::from_raw_counts(biases.len(), iterations, counts);
+    result.expected_by_outcome = Some(expected_by_outcome);
+    Ok(result)
+}
+
+/// Plays `iterations` games of gambler's ruin and gets the empirical probability of reaching
+/// `target` before going broke.
+///
+/// Each game starts with a bankroll of `start` and flips a coin that lands heads with
+/// probability `p_heads` until the bankroll reaches 0 or `target`, winning 1 on heads and losing 1
+/// on tails. For a fair coin the probability of reaching the target is `start / target`. Returns
+/// a [`SimError`] unless `0 < start < target` and `p_heads` is between 0 and 1.
+///
+/// # Examples
+/// ```
+/// use coin_flip_simulation;
+///
+/// let result = coin_flip_simulation::run_gamblers_ruin(5, 10, 1000, 0.5).unwrap();
+///
+/// assert!((result.probability - 0.5).abs() < 0.1);
+/// ```
+pub fn run_gamblers_ruin(
+    start: u64,
+    target: u64,
+    iterations: usize,
+    p_heads: f64,
+) -> Result<EmpiricalResult, SimError> {
+    let p_heads = validate_bias(p_heads)?;
+    if start == 0 || start >= target {
+        return Err(SimError::InvalidBankroll { start, target });
+    }
+
+    let mut rng = rand::thread_rng();
+    let mut wins = 0;
+    for _ in 0..iterations {
+        let mut bankroll = start;
+        while bankroll > 0 && bankroll < target {
+            if rng.gen_bool(p_heads) {
+                bankroll += 1;
+            } else {
+                bankroll -= 1;
+            }
+        }
+
+        if bankroll == target {
+            wins += 1;
+        }
+    }
+
+    Ok(EmpiricalResult::new(wins, iterations))
+}
+
+/// Runs a coin flip simulation that writes each iteration to `writer` as a line of NDJSON, such
+/// as `{"iteration":0,"outcome":"HTH"}`, flushing after every line.
+///
+/// # Examples
+/// ```
+/// use coin_flip_simulation;
+///
+/// let mut ndjson = Vec::new();
+/// let result = coin_flip_simulation::run_streaming(3, 10, &mut ndjson).unwrap();
+///
+/// assert_eq!(ndjson.iter().filter(|&&b| b == b'\n').count(), 10);
+/// assert_eq!(result.iterations, 10);
+/// ```
+pub fn run_streaming<W: Write>(
+    flips_per_iteration: usize,
+    iterations: usize,
+    writer: &mut W,
+) -> io::Result<CoinFlipResult> {
+    run_streaming_batched(flips_per_iteration, iterations, writer, 1)
+}
+
+/// Runs a coin flip simulation that writes each iteration to `writer` as a line of NDJSON,
+/// flushing after every `batch_size` lines and once more after any remainder.
+///
+/// Flushing less often speeds up writers that buffer output for a slow sink, such as a
+/// `BufWriter` around a file or socket. A `batch_size` of 0 is treated as 1.
+///
+/// # Examples
+/// ```
+/// use std::io::BufWriter;
+/// use coin_flip_simulation;
+///
+/// let mut writer = BufWriter::new(Vec::new());
+/// coin_flip_simulation::run_streaming_batched(3, 1000, &mut writer, 100).unwrap();
+///
+/// assert_eq!(writer.get_ref().iter().filter(|&&b| b == b'\n').count(), 1000);
+/// ```
+pub fn run_streaming_batched<W: Write>(
+    flips_per_iteration: usize,
+    iterations: usize,
+    writer: &mut W,
+    batch_size: usize,
+) -> io::Result<CoinFlipResult> {
+    let batch_size = batch_size.max(1);
+    let mut results: BTreeMap<String, usize> = get_all_outcomes(flips_per_iteration)
+        .into_iter()
+        .map(|outcome| (outcome, 0))
+        .collect();
+
+    for i in 0..iterations {
+        let outcome = flip_outcome(flips_per_iteration);
+        writeln!(writer, "{{\"iteration\":{i},\"outcome\":\"{outcome}\"}}")?;
+        *results.entry(outcome).or_insert(0) += 1;
+
+        if (i + 1).is_multiple_of(batch_size) {
+            writer.flush()?;
+        }
+    }
+    if !iterations.is_multiple_of(batch_size) {
+        writer.flush()?;
+    }
+
+    Ok(CoinFlipResult::from_raw_counts(
+        flips_per_iteration,
+        iterations,
+        results,
+    ))
+}
+
+/// Runs a coin flip simulation that records both each outcome and the number of heads in it, in
+/// a single pass.
+///
+/// # Examples
+/// ```
+/// use coin_flip_simulation;
+///
+/// let full = coin_flip_simulation::run_full(3, 8000);
+///
+/// assert_eq!(full.sequences.results.len(), 8);
+/// assert_eq!(full.head_counts.len(), 4);
+/// ```
+pub fn run_full(flips_per_iteration: usize, iterations: usize) -> FullResult {
+    let mut results: BTreeMap<String, usize> = get_all_outcomes(flips_per_iteration)
+        .into_iter()
+        .map(|outcome| (outcome, 0))
+        .collect();
+    let mut head_counts: BTreeMap<usize, usize> =
+        (0..=flips_per_iteration).map(|heads| (heads, 0)).collect();
+
+    for _ in 0..iterations {
+        let outcome = flip_outcome(flips_per_iteration);
+        *head_counts.entry(num_heads(&outcome)).or_insert(0) += 1;
+        *results.entry(outcome).or_insert(0) += 1;
+    }
+
+    FullResult {
+        sequences: CoinFlipResult::from_raw_counts(flips_per_iteration, iterations, results),
+        head_counts: head_counts
+            .into_iter()
+            .map(|(heads, count)| (heads, EmpiricalResult::new(count, iterations)))
+            .collect(),
+    }
+}
+
+/// Enumerates every outcome exactly once rather than flipping at random, giving the exact
+/// uniform distribution over the `2^flips_per_iteration` outcomes as an ideal baseline.
+///
+/// # Examples
+/// ```
+/// use coin_flip_simulation;
+///
+/// let result = coin_flip_simulation::run_exhaustive(3);
+///
+/// assert_eq!(result.iterations, 8);
+/// assert!(result.results.values().all(|r| r.count == 1));
+/// ```
+pub fn run_exhaustive(flips_per_iteration: usize) -> CoinFlipResult {
+    let results: BTreeMap<String, usize> = get_all_outcomes(flips_per_iteration)
+        .into_iter()
+        .map(|outcome| (outcome, 1))
+        .collect();
+    let iterations = results.len();
+
+    CoinFlipResult::from_raw_counts(flips_per_iteration, iterations, results)
+}
+
+/// Gets a vector of all possible outcomes as strings.
+///
+/// # Examples
+/// ```
+/// use coin_flip_simulation;
+///
+/// let outcomes = coin_flip_simulation::get_all_outcomes(3);
+///
+/// assert_eq!(outcomes, vec!["HHH", "HHT", "HTH", "HTT", "THH", "THT", "TTH", "TTT"]);
+/// ```
+pub fn get_all_outcomes(flips_per_iteration: usize) -> Vec<String> {
+    get_all_outcomes_with_symbols(flips_per_iteration, ['H', 'T'])
+}
+
+/// Gets a vector of all possible outcomes as strings of the heads and tails symbols.
+///
+/// Outcomes are generated in the same order as [`get_all_outcomes`], starting from all heads,
+/// which may differ from their sorted order.
+///
+/// # Panics
+///
+/// Panics if the number of outcomes, `2^flips_per_iteration`, overflows a `usize`.
+///
+/// # Examples
+/// ```
+/// use coin_flip_simulation;
+///
+/// let outcomes = coin_flip_simulation::get_all_outcomes_with_symbols(2, ['1', '0']);
+///
+/// assert_eq!(outcomes, vec!["11", "10", "01", "00"]);
+/// ```
+pub fn get_all_outcomes_with_symbols(
+    flips_per_iteration: usize,
+    symbols: [char; 2],
+) -> Vec<String> {
+    outcomes_over_symbols(flips_per_iteration, &symbols)
+}
+
+/// Gets every sequence of `flips_per_iteration` symbols, counting up in base `symbols.len()` with
+/// the first symbol as the digit 0 and the first flip as the most significant digit.
+///
+/// Panics if the number of outcomes overflows a `usize`, so callers that take the number of flips
+/// from users should check it first.
+fn outcomes_over_symbols(flips_per_iteration: usize, symbols: &[char]) -> Vec<String> {
+    let num_outcomes = u32::try_from(flips_per_iteration)
+        .ok()
+        .and_then(|flips| symbols.len().checked_pow(flips))
+        .expect("number of outcomes overflows a usize");
+    let mut results = Vec::with_capacity(num_outcomes);
+
+    for i in 0..num_outcomes {
+        let mut outcome = String::with_capacity(flips_per_iteration);
+        for j in 1..=flips_per_iteration {
+            let d = num_outcomes / symbols.len().pow(j as u32);
+            outcome.push(symbols[(i / d) % symbols.len()]);
+        }
+
+        results.push(outcome);
+    }
+
+    results
+}
+
+/// Gets a vector of the `C(flips_per_iteration, k)` outcomes with exactly `k` heads, in the same
+/// order as [`get_all_outcomes`].
+///
+/// Only the matching outcomes are generated, so this is practical even when the full set of
+/// `2^flips_per_iteration` outcomes isn't. There are none if `k` is greater than
+/// `flips_per_iteration`.
+///
+/// # Examples
+/// ```
+/// use coin_flip_simulation;
+///
+/// let outcomes = coin_flip_simulation::outcomes_with_k_heads(3, 2);
+///
+/// assert_eq!(outcomes, vec!["HHT", "HTH", "THH"]);
+/// ```
+pub fn outcomes_with_k_heads(flips_per_iteration: usize, k: usize) -> Vec<String> {
+    fn extend(
+        prefix: &mut String,
+        flips_left: usize,
+        heads_left: usize,
+        outcomes: &mut Vec<String>,
+    ) {
+        if flips_left == 0 {
+            outcomes.push(prefix.clone());
+            return;
+        }
+
+        if heads_left > 0 {
+            prefix.push('H');
+            extend(prefix, flips_left - 1, heads_left - 1, outcomes);
+            prefix.pop();
+        }
+        if flips_left > heads_left {
+            prefix.push('T');
+            extend(prefix, flips_left - 1, heads_left, outcomes);
+            prefix.pop();
+        }
+    }
+
+    let mut outcomes = Vec::new();
+    if k <= flips_per_iteration {
+        extend(
+            &mut String::with_capacity(flips_per_iteration),
+            flips_per_iteration,
+            k,
+            &mut outcomes,
+        );
+    }
+
+    outcomes
+}
+
+/// Gets the position of an outcome in [`get_all_outcomes`], reading heads as a 0 bit and tails
+/// as a 1 bit with the first flip most significant.
+///
+/// Returns `None` if the outcome contains anything other than `H` or `T`, or is too long for its
+/// index to fit in a `usize`.
+///
+/// # Examples
+/// ```
+/// use coin_flip_simulation;
+///
+/// assert_eq!(coin_flip_simulation::outcome_to_index("HTT"), Some(3));
+/// assert_eq!(coin_flip_simulation::outcome_to_index("HXT"), None);
+/// ```
+pub fn outcome_to_index(outcome: &str) -> Option<usize> {
+    outcome_to_index_with_symbols(outcome, ['H', 'T'])
+}
+
+/// Gets the position of an outcome in [`get_all_outcomes_with_symbols`] for the same symbols.
+///
+/// # Examples
+/// ```
+/// use coin_flip_simulation;
+///
+/// assert_eq!(coin_flip_simulation::outcome_to_index_with_symbols("01", ['1', '0']), Some(2));
+/// ```
+pub fn outcome_to_index_with_symbols(outcome: &str, symbols: [char; 2]) -> Option<usize> {
+    outcome_index_over_symbols(outcome, &symbols)
+}
+
+/// Gets the position of an outcome among all outcomes over `symbols` in the order they're
+/// enumerated, reading it as a number in base `symbols.len()` with the first flip most
+/// significant.
+///
+/// Returns `None` if the outcome contains a character that isn't one of `symbols`, or if the
+/// number of outcomes of its length overflows a `usize`.
+fn outcome_index_over_symbols(outcome: &str, symbols: &[char]) -> Option<usize> {
+    symbols
+        .len()
+        .checked_pow(u32::try_from(outcome.chars().count()).ok()?)?;
+
+    outcome.chars().try_fold(0, |index, c| {
+        let digit = symbols.iter().position(|&symbol| symbol == c)?;
+        Some(index * symbols.len() + digit)
+    })
+}
+
+/// Checks that every outcome from [`get_all_outcomes`] sits at the index [`outcome_to_index`]
+/// gives it, returning the first index where they disagree.
+#[cfg(test)]
+fn check_outcome_indexing(flips_per_iteration: usize) -> Result<(), usize> {
+    for (i, outcome) in get_all_outcomes(flips_per_iteration).iter().enumerate() {
+        if outcome_to_index(outcome) != Some(i) {
+            return Err(i);
+        }
+    }
+
+    Ok(())
+}
+
+/// Gets the number of possible outcomes.
+///
+/// ```
+/// use coin_flip_simulation;
+///
+/// let num_outcomes = coin_flip_simulation::get_num_outcomes(3);
+///
+/// assert_eq!(num_outcomes, 8);
+/// ```
+pub fn get_num_outcomes(flips_per_iteration: usize) -> usize {
+    let base: usize = 2;
+    base.pow(flips_per_iteration as u32)
+}
+
+/// Gets the expected length of the longest run of heads in `flips_per_iteration` fair flips.
+///
+/// This is computed exactly rather than approximated, as `E[L] = sum_k P(L >= k)`. The
+/// probability `q(m)` of no run of `k` heads in `m` flips is 1 for `m < k`, `1 - 2^-k` for
+/// `m = k`, and `q(m - 1) - q(m - k - 1) / 2^(k + 1)` after that, since a first run of `k` heads
+/// ending at flip `m` must follow a tail and a prefix without such a run. Terms smaller than
+/// `1e-17` are dropped, so the result is accurate to within floating point error. For large `n`
+/// it grows like `log2(n) - 2/3`.
+///
+/// # Examples
+/// ```
+/// use coin_flip_simulation;
+///
+/// assert_eq!(coin_flip_simulation::expected_longest_run(1), 0.5);
+/// assert_eq!(coin_flip_simulation::expected_longest_run(3), 1.375);
+/// ```
+pub fn expected_longest_run(flips_per_iteration: usize) -> f64 {
+    let n = flips_per_iteration;
+    let mut expected = 0.0;
+
+    for k in 1..=n {
+        let mut q = vec![1.0; n + 1];
+        q[k] = 1.0 - 0.5f64.powi(k as i32);
+        for m in (k + 1)..=n {
+            q[m] = q[m - 1] - q[m - k - 1] * 0.5f64.powi(k as i32 + 1);
+        }
+
+        let p_at_least_k = 1.0 - q[n];
+        if p_at_least_k < 1e-17 {
+            break;
+        }
+        expected += p_at_least_k;
+    }
+
+    expected
+}
+
+/// Estimates the size of a simulation without running it.
+///
+/// The memory estimate counts each outcome's key, its heap-allocated string and its empirical
+/// result, plus a rough per-entry overhead for the map. It's only meant to give the order of
+/// magnitude of the result's footprint. A simulation whose estimate is more than
+/// [`MAX_RESULT_BYTES`] is rejected by [`try_run`].
+///
+/// # Examples
+/// ```
+/// use coin_flip_simulation;
+///
+/// let estimate = coin_flip_simulation::estimate(3, 8000);
+///
+/// assert_eq!(estimate.num_outcomes, Some(8));
+/// assert!(!estimate.overflows);
+/// assert!(coin_flip_simulation::estimate(63, 8000).exceeds_limit);
+/// assert!(coin_flip_simulation::estimate(200, 8000).overflows);
+/// ```
+pub fn estimate(flips_per_iteration: usize, iterations: usize) -> SimEstimate {
+    let num_outcomes = u32::try_from(flips_per_iteration)
+        .ok()
+        .and_then(|flips| 2usize.checked_pow(flips));
+
+    let estimated_bytes = num_outcomes.and_then(|n| result_bytes(n, flips_per_iteration));
+
+    SimEstimate {
+        flips_per_iteration,
+        iterations,
+        num_outcomes,
+        estimated_bytes,
+        overflows: num_outcomes.is_none(),
+        exceeds_limit: estimated_bytes.is_none_or(|bytes| bytes > MAX_RESULT_BYTES),
+    }
+}
+
+/// Estimates the size of a result map of `num_outcomes` outcomes of `flips_per_iteration` flips,
+/// or `None` if it overflows a `usize`.
+fn result_bytes(num_outcomes: usize, flips_per_iteration: usize) -> Option<usize> {
+    let entry_bytes = std::mem::size_of::<String>()
+        + std::mem::size_of::<EmpiricalResult>()
+        + MAP_ENTRY_OVERHEAD_BYTES;
+
+    entry_bytes
+        .checked_add(flips_per_iteration)
+        .and_then(|bytes| bytes.checked_mul(num_outcomes))
+}
+
+/// Measures the throughput of [`run`] on the current machine.
+///
+/// A warmup run of a tenth of the iterations comes first so the timed run isn't skewed by cold
+/// caches, then `iterations` iterations are timed. The memory used for the result map is
+/// estimated as in [`estimate`].
+///
+/// # Examples
+/// ```
+/// use coin_flip_simulation;
+///
+/// let report = coin_flip_simulation::benchmark(10, 10000);
+///
+/// assert!(report.flips_per_sec > report.iterations_per_sec);
+/// ```
+pub fn benchmark(flips_per_iteration: usize, iterations: usize) -> BenchReport {
+    run(flips_per_iteration, iterations / 10);
+
+    let start = Instant::now();
+    let result = run(flips_per_iteration, iterations);
+    let elapsed = start.elapsed();
+
+    let iterations_per_sec = result.iterations as f64 / elapsed.as_secs_f64();
+    BenchReport {
+        elapsed,
+        flips_per_sec: iterations_per_sec * flips_per_iteration as f64,
+        iterations_per_sec,
+        result_bytes: estimate(flips_per_iteration, iterations)
+            .estimated_bytes
+            .unwrap_or(usize::MAX),
+    }
+}
+
+/// A rough estimate of the per-entry bookkeeping of a `BTreeMap`.
+const MAP_ENTRY_OVERHEAD_BYTES: usize = 16;
+
+/// The largest estimated result size, in bytes, that [`try_run`] and the other fallible run
+/// functions accept, which is 1 GiB.
+///
+/// On a 64-bit target this allows up to 23 flips per iteration.
+pub const MAX_RESULT_BYTES: usize = 1 << 30;
+
+/// Gets the approximate critical value of the chi-square distribution with `df` degrees of
+/// freedom at significance level `alpha`, using the Wilson–Hilferty approximation.
+///
+/// A chi-square statistic above this value rejects fairness at level `alpha`; a result from
+/// [`run`] with `n` flips per iteration has `2^n - 1` degrees of freedom. The approximation is
+/// within about 0.5% of the exact value for `df >= 3` and `0.001 <= alpha <= 0.5`, but
+/// underestimates by up to about 2.5% at `df` of 1 or 2. Returns NaN unless `df > 0` and
+/// `0 < alpha < 1`.
+///
+/// # Examples
+/// ```
+/// use coin_flip_simulation;
+///
+/// let critical = coin_flip_simulation::chi_square_critical(7, 0.05);
+///
+/// assert!((critical - 14.067).abs() < 0.05);
+/// ```
+pub fn chi_square_critical(df: usize, alpha: f64) -> f64 {
+    if df == 0 || !(alpha > 0.0 && alpha < 1.0) {
+        return f64::NAN;
+    }
+
+    let df = df as f64;
+    let variance = 2.0 / (9.0 * df);
+    let z = normal_quantile(1.0 - alpha);
+
+    df * (1.0 - variance + z * variance.sqrt()).powi(3)
+}
+
+/// Gets a bound on the largest absolute deviation of any outcome's empirical probability from
+/// its expected probability that holds with probability `confidence` for a fair coin.
+///
+/// Each of the `k = 2^flips_per_iteration` outcomes is given a two-sided normal interval at the
+/// Bonferroni-corrected level `1 - (1 - confidence) / k`, so the bound is
+/// `z * sqrt(p * (1 - p) / iterations)` with `p = 1 / k`. The bound is conservative, and shrinks
+/// like `1 / sqrt(iterations)`. Returns NaN unless `0 < confidence < 1`.
+///
+/// # Examples
+/// ```
+/// use coin_flip_simulation;
+///
+/// let band = coin_flip_simulation::expected_sup_deviation(3, 8000, 0.95);
+///
+/// assert!(band > 0.0 && band < 0.02);
+/// ```
+pub fn expected_sup_deviation(
+    flips_per_iteration: usize,
+    iterations: usize,
+    confidence: f64,
+) -> f64 {
+    if !(confidence > 0.0 && confidence < 1.0) {
+        return f64::NAN;
+    }
+
+    let num_outcomes = get_num_outcomes(flips_per_iteration) as f64;
+    let p = 1.0 / num_outcomes;
+    let z = normal_quantile(1.0 - (1.0 - confidence) / (2.0 * num_outcomes));
+
+    z * (p * (1.0 - p) / iterations as f64).sqrt()
+}
+
+/// Gets the quantile of the standard normal distribution at `p`, for `0 < p < 1`, using Acklam's
+/// rational approximation.
+fn normal_quantile(p: f64) -> f64 {
+    const A: [f64; 6] = [
+        -3.969_683_028_665_376e1,
+        2.209_460_984_245_205e2,
+        -2.759_285_104_469_687e2,
+        1.383_577_518_672_69e2,
+        -3.066_479_806_614_716e1,
+        2.506_628_277_459_239,
+    ];
+    const B: [f64; 5] = [
+        -5.447_609_879_822_406e1,
+        1.615_858_368_580_409e2,
+        -1.556_989_798_598_866e2,
+        6.680_131_188_771_972e1,
+        -1.328_068_155_288_572e1,
+    ];
+    const C: [f64; 6] = [
+        -7.784_894_002_430_293e-3,
+        -3.223_964_580_411_365e-1,
+        -2.400_758_277_161_838,
+        -2.549_732_539_343_734,
+        4.374_664_141_464_968,
+        2.938_163_982_698_783,
+    ];
+    const D: [f64; 4] = [
+        7.784_695_709_041_462e-3,
+        3.224_671_290_700_398e-1,
+        2.445_134_137_142_996,
+        3.754_408_661_907_416,
+    ];
+    const P_LOW: f64 = 0.024_25;
+
+    let tail = |q: f64| {
+        (((((C[0] * q + C[1]) * q + C[2]) * q + C[3]) * q + C[4]) * q + C[5])
+            / ((((D[0] * q + D[1]) * q + D[2]) * q + D[3]) * q + 1.0)
+    };
+
+    if p < P_LOW {
+        tail((-2.0 * p.ln()).sqrt())
+    } else if p > 1.0 - P_LOW {
+        -tail((-2.0 * (1.0 - p).ln()).sqrt())
+    } else {
+        let q = p - 0.5;
+        let r = q * q;
+        (((((A[0] * r + A[1]) * r + A[2]) * r + A[3]) * r + A[4]) * r + A[5]) * q
+            / (((((B[0] * r + B[1]) * r + B[2]) * r + B[3]) * r + B[4]) * r + 1.0)
+    }
+}
+
+/// Gets the expected number of distinct outcomes observed in `iterations` iterations of
+/// `flips_per_iteration` flips each.
+///
+/// With `n` equally likely outcomes this is `n * (1 - (1 - 1/n)^iterations)`, which approaches `n`
+/// as the number of iterations grows.
+///
+/// # Examples
+/// ```
+/// use coin_flip_simulation;
+///
+/// assert_eq!(coin_flip_simulation::expected_distinct_outcomes(1, 1), 1.0);
+/// assert!((coin_flip_simulation::expected_distinct_outcomes(1, 2) - 1.5).abs() < 1e-12);
+/// ```
+pub fn expected_distinct_outcomes(flips_per_iteration: usize, iterations: usize) -> f64 {
+    let num_outcomes = 2f64.powi(flips_per_iteration as i32);
+    let miss_probability = (1.0 - 1.0 / num_outcomes).powf(iterations as f64);
+
+    num_outcomes * (1.0 - miss_probability)
+}
+
+/// Gets the expected number of possibly overlapping occurrences of `pattern` in
+/// `flips_per_iteration` flips of a coin that lands heads with probability `p_heads`.
+///
+/// By linearity of expectation this is the number of windows the pattern fits in times the
+/// probability of any one window matching, so self-overlapping patterns such as `HH` need no
+/// special handling. Returns a [`SimError`] if `p_heads` isn't between 0 and 1, or if the pattern
+/// is empty, contains anything other than `H` or `T`, or is longer than `flips_per_iteration`.
+///
+/// # Examples
+/// ```
+/// use coin_flip_simulation;
+///
+/// let expected = coin_flip_simulation::expected_substring_occurrences(3, "HH", 0.5).unwrap();
+///
+/// assert_eq!(expected, 0.5);
+/// ```
+pub fn expected_substring_occurrences(
+    flips_per_iteration: usize,
+    pattern: &str,
+    p_heads: f64,
+) -> Result<f64, SimError> {
+    let p_heads = validate_bias(p_heads)?;
+    let pattern: Vec<char> = pattern.chars().collect();
+    validate_pattern(&pattern, flips_per_iteration)?;
+
+    let heads = pattern.iter().filter(|&&c| c == 'H').count();
+    let tails = pattern.len() - heads;
+    let window_probability = p_heads.powi(heads as i32) * (1.0 - p_heads).powi(tails as i32);
+
+    Ok((flips_per_iteration - pattern.len() + 1) as f64 * window_probability)
+}
+
+/// Gets the variance of the count of an outcome over `iterations` iterations, which is the
+/// binomial variance `n * p * (1 - p)`.
+///
+/// `p_outcome` is the probability of the outcome in a single iteration, or `None` for the
+/// probability of any one of the `2^flips_per_iteration` outcomes of a fair coin. Together with the
+/// expected count `n * p` this gives a normal-approximation band for the observed count.
+///
+/// # Examples
+/// ```
+/// use coin_flip_simulation;
+///
+/// let variance = coin_flip_simulation::expected_count_variance(3, 8000, None);
+///
+/// assert_eq!(variance, 875.0);
+/// ```
+pub fn expected_count_variance(
+    flips_per_iteration: usize,
+    iterations: usize,
+    p_outcome: Option<f64>,
+) -> f64 {
+    let p = p_outcome.unwrap_or_else(|| 0.5f64.powi(flips_per_iteration as i32));
+
+    iterations as f64 * p * (1.0 - p)
+}
+
+/// Gets the expected number of fair flips until `pattern` first appears, using Conway's
+/// leading numbers.
+///
+/// The expected wait is the sum of `2^k` over every `k` for which the first `k` flips of the
+/// pattern equal its last `k`, so self-overlapping patterns such as `HTH` wait longer than ones
+/// of the same length that don't overlap themselves, such as `HTT`. Returns a [`SimError`] if
+/// the pattern is empty or contains anything other than `H` or `T`.
+///
+/// # Examples
+/// ```
+/// use coin_flip_simulation;
+///
+/// assert_eq!(coin_flip_simulation::expected_wait_time("HTH"), Ok(10.0));
+/// assert_eq!(coin_flip_simulation::expected_wait_time("HTT"), Ok(8.0));
+/// ```
+pub fn expected_wait_time(pattern: &str) -> Result<f64, SimError> {
+    let pattern: Vec<char> = pattern.chars().collect();
+    validate_pattern(&pattern, usize::MAX)?;
+
+    Ok((1..=pattern.len())
+        .filter(|&k| pattern[..k] == pattern[pattern.len() - k..])
+        .map(|k| 2f64.powi(k as i32))
+        .sum())
+}
+
+/// Counts the number of heads in an outcome string.
+fn num_heads(outcome: &str) -> usize {
+    outcome.matches('H').count()
+}
+
+/// Represents the result of running the coin flip simulation.
+///
+/// Contains the emprical results, the number of iterations, and the expected result, along with
+/// the number of flips per iteration and the symbols an outcome can be made of.
+///
+/// `expected` is the result of each outcome if they were all equally likely, even for results
+/// whose outcomes aren't, such as those of [`run_with_edge`] or after
+/// [`CoinFlipResult::set_expected_map`].
+#[derive(Clone)]
+pub struct CoinFlipResult {
+    pub flips_per_iteration: usize,
+    pub symbols: Vec<char>,
+    pub iterations: usize,
+    pub expected: EmpiricalResult,
+    pub results: BTreeMap<String, EmpiricalResult>,
+    num_outcomes: usize,
+    expected_probability: f64,
+    expected_by_outcome: Option<BTreeMap<String, f64>>,
+}
+
+impl CoinFlipResult {
+    fn new(
+        flips_per_iteration: usize,
+        iterations: usize,
+        expected: EmpiricalResult,
+        num_outcomes: usize,
+        results: BTreeMap<String, EmpiricalResult>,
+    ) -> Self {
+        CoinFlipResult {
+            flips_per_iteration,
+            symbols: vec!['H', 'T'],
+            iterations,
+            expected,
+            results,
+            num_outcomes,
+            expected_probability: 1.0 / num_outcomes as f64,
+            expected_by_outcome: None,
+        }
+    }
+
+    fn from_raw_counts(
+        flips_per_iteration: usize,
+        iterations: usize,
+        counts: BTreeMap<String, usize>,
+    ) -> Self {
+        let results = counts
+            .into_iter()
+            .map(|(key, count)| (key, EmpiricalResult::new(count, iterations)))
+            .collect();
+
+        let expected = EmpiricalResult::expected(flips_per_iteration, iterations);
+        let num_outcomes = get_num_outcomes(flips_per_iteration);
+        CoinFlipResult::new(
+            flips_per_iteration,
+            iterations,
+            expected,
+            num_outcomes,
+            results,
+        )
+    }
+
+    /// Creates a result from explicit counts of each outcome, such as tallies from elsewhere,
+    /// without simulating.
+    ///
+    /// The number of iterations is the sum of the counts, and outcomes missing from `counts` are
+    /// included with a count of 0 like in [`run`]. Returns a [`SimError`] if a key isn't an
+    /// outcome of `flips_per_iteration` heads or tails, if the number of possible outcomes
+    /// overflows a `usize` or they'd be too large as in [`try_run`], or if the counts sum to 0.
+    ///
+    /// # Examples
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use coin_flip_simulation::CoinFlipResult;
+    ///
+    /// let counts = BTreeMap::from([("HH".to_string(), 3), ("TT".to_string(), 1)]);
+    /// let result = CoinFlipResult::from_counts(2, counts).unwrap();
+    ///
+    /// assert_eq!(result.iterations, 4);
+    /// assert_eq!(result.results["HH"].probability, 0.75);
+    /// assert_eq!(result.results["HT"].count, 0);
+    /// ```
+    pub fn from_counts(
+        flips_per_iteration: usize,
+        counts: BTreeMap<String, usize>,
+    ) -> Result<Self, SimError> {
+        if flips_per_iteration >= usize::BITS as usize {
+            return Err(SimError::Overflow {
+                flips_per_iteration,
+            });
+        }
+        if estimate(flips_per_iteration, 0).exceeds_limit {
+            return Err(SimError::TooLarge {
+                flips_per_iteration,
+            });
+        }
+        let is_outcome = |key: &String| {
+            key.chars().count() == flips_per_iteration && key.chars().all(|c| c == 'H' || c == 'T')
+        };
+        if let Some(key) = counts.keys().find(|key| !is_outcome(key)) {
+            return Err(SimError::InvalidOutcome(key.clone()));
+        }
+
+        let iterations = counts.values().sum();
+        if iterations == 0 {
+            return Err(SimError::ZeroIterations);
+        }
+
+        let mut results: BTreeMap<String, usize> = get_all_outcomes(flips_per_iteration)
+            .into_iter()
+            .map(|outcome| (outcome, 0))
+            .collect();
+        results.extend(counts);
+
+        Ok(CoinFlipResult::from_raw_counts(
+            flips_per_iteration,
+            iterations,
+            results,
+        ))
+    }
+
+    /// Counts the number of heads symbols in an outcome.
+    fn heads_in(&self, outcome: &str) -> usize {
+        outcome.chars().filter(|&c| c == self.symbols[0]).count()
+    }
+
+    /// Gets the exact expected probability of each outcome.
+    ///
+    /// Unlike `expected.probability`, this isn't derived from the integer `expected.count`, so it
+    /// doesn't lose precision when `iterations` isn't divisible by the number of outcomes. For
+    /// results whose outcomes aren't equally likely, this is the probability of each outcome if
+    /// they were; [`Self::iter_with_expected`] gives the actual expected probability of each.
+    pub fn expected_probability(&self) -> f64 {
+        self.expected_probability
+    }
+
+    /// Gets the expected probability of `outcome`, which differs between outcomes for results
+    /// such as those of [`run_with_edge`].
+    fn expected_probability_of(&self, outcome: &str) -> f64 {
+        match &self.expected_by_outcome {
+            Some(expected) => expected.get(outcome).copied().unwrap_or(0.0),
+            None => self.expected_probability,
+        }
+    }
+
+    /// Replaces the expected probability of each outcome, such as with the distribution of a
+    /// biased coin, for every method that compares the results with what was expected, such as
+    /// [`Self::chi_square`] and [`Self::comparison`].
+    ///
+    /// The uniform `expected`, [`Self::expected_probability`] and [`Self::expected_float_count`]
+    /// are left as they are. Returns a [`SimError`] if a key isn't a valid outcome, if an outcome
+    /// in `results` has no expected probability, if a probability isn't finite and positive, or if
+    /// the probabilities don't sum to 1.
+    ///
+    /// # Examples
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use coin_flip_simulation;
+    ///
+    /// let mut result = coin_flip_simulation::run(1, 1000);
+    /// let expected = BTreeMap::from([("H".to_string(), 0.9), ("T".to_string(), 0.1)]);
+    /// result.set_expected_map(expected).unwrap();
+    ///
+    /// assert_eq!(result.comparison()[0].expected, 0.9);
+    /// ```
+    pub fn set_expected_map(&mut self, expected: BTreeMap<String, f64>) -> Result<(), SimError> {
+        if let Some(outcome) = expected
+            .keys()
+            .find(|outcome| !self.is_valid_outcome(outcome))
+        {
+            return Err(SimError::InvalidOutcome(outcome.clone()));
+        }
+        let missing = self
+            .results
+            .keys()
+            .find(|outcome| !expected.contains_key(*outcome));
+        if let Some(outcome) = missing {
+            return Err(SimError::MissingExpected(outcome.clone()));
+        }
+        let invalid = expected.iter().find(|(_, p)| !(p.is_finite() && **p > 0.0));
+        if let Some((outcome, &probability)) = invalid {
+            return Err(SimError::InvalidProbability {
+                outcome: outcome.clone(),
+                probability,
+            });
+        }
+
+        let total: f64 = expected.values().sum();
+        if (total - 1.0).abs() > PROBABILITY_SUM_TOLERANCE {
+            return Err(SimError::ProbabilitySum(total));
+        }
+
+        self.expected_by_outcome = Some(expected);
+        Ok(())
+    }
+
+    /// Gets the exact expected count of each outcome as a float, if they were all equally likely.
+    ///
+    /// Like [`Self::expected_probability`], this is uniform even for results with their own
+    /// expected probabilities; [`Self::iter_with_expected`] gives those.
+    ///
+    /// # Examples
+    /// ```
+    /// use coin_flip_simulation;
+    ///
+    /// let result = coin_flip_simulation::run(3, 10);
+    ///
+    /// assert_eq!(result.expected.count, 1);
+    /// assert_eq!(result.expected_float_count(), 1.25);
+    /// ```
+    pub fn expected_float_count(&self) -> f64 {
+        self.expected_probability * self.iterations as f64
+    }
+
+    /// Gets the number of outcomes in the result space.
+    fn num_outcomes(&self) -> usize {
+        self.num_outcomes
+    }
+
+    /// Gets a warning message if there are too few iterations for the empirical probabilities to
+    /// be reliable, which is fewer than 5 iterations per possible outcome.
+    ///
+    /// # Examples
+    /// ```
+    /// use coin_flip_simulation;
+    ///
+    /// assert!(coin_flip_simulation::run(10, 100).sample_size_warning().is_some());
+    /// assert!(coin_flip_simulation::run(3, 8000).sample_size_warning().is_none());
+    /// ```
+    pub fn sample_size_warning(&self) -> Option<String> {
+        let num_outcomes = self.num_outcomes();
+        let min_iterations = MIN_ITERATIONS_PER_OUTCOME * num_outcomes;
+
+        if self.iterations < min_iterations {
+            Some(format!(
+                "{} iterations is too few for {num_outcomes} possible outcomes, \
+                 use at least {min_iterations} for reliable probabilities",
+                self.iterations,
+            ))
+        } else {
+            None
+        }
+    }
+
+    /// Checks whether `key` is an outcome in the result space.
+    ///
+    /// A valid outcome has exactly `flips_per_iteration` symbols, each of which is one of the
+    /// result's `symbols`.
+    ///
+    /// # Examples
+    /// ```
+    /// use coin_flip_simulation;
+    ///
+    /// let result = coin_flip_simulation::run(3, 100);
+    ///
+    /// assert!(result.is_valid_outcome("HTH"));
+    /// assert!(!result.is_valid_outcome("HT"));
+    /// assert!(!result.is_valid_outcome("HXH"));
+    /// ```
+    pub fn is_valid_outcome(&self, key: &str) -> bool {
+        key.chars().count() == self.flips_per_iteration
+            && key.chars().all(|c| self.symbols.contains(&c))
+    }
+
+    /// Gets the empirical probability of an outcome, or 0 if a valid outcome isn't in the results.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `key` isn't a valid outcome, as checked by [`Self::is_valid_outcome`].
+    ///
+    /// # Examples
+    /// ```
+    /// use coin_flip_simulation;
+    ///
+    /// let result = coin_flip_simulation::run_without_replacement(2, 1, 100).unwrap();
+    ///
+    /// assert_eq!(result.probability_of("TTH"), 0.0);
+    /// assert_eq!(result.probability_of("HHT"), result.results["HHT"].probability);
+    /// ```
+    pub fn probability_of(&self, key: &str) -> f64 {
+        assert!(self.is_valid_outcome(key), "{key:?} is not a valid outcome");
+
+        self.results
+            .get(key)
+            .map_or(0.0, |result| result.probability)
+    }
+
+    /// Gets the empirical probability of an outcome containing at least `k` heads.
+    ///
+    /// # Examples
+    /// ```
+    /// use coin_flip_simulation;
+    ///
+    /// let result = coin_flip_simulation::run(3, 8000);
+    ///
+    /// assert!((result.probability_at_least_k_heads(2) - 0.5).abs() < 0.05);
+    /// assert_eq!(result.probability_at_least_k_heads(4), 0.0);
+    /// ```
+    pub fn probability_at_least_k_heads(&self, k: usize) -> f64 {
+        self.results
+            .iter()
+            .filter(|(outcome, _)| self.heads_in(outcome) >= k)
+            .map(|(_, result)| result.probability)
+            .sum()
+    }
+
+    /// Gets the empirical probability of an outcome containing at most `k` heads.
+    pub fn probability_at_most_k_heads(&self, k: usize) -> f64 {
+        self.results
+            .iter()
+            .filter(|(outcome, _)| self.heads_in(outcome) <= k)
+            .map(|(_, result)| result.probability)
+            .sum()
+    }
+
+    /// Compares each outcome's observed probability with the expected probability.
+    ///
+    /// The relative error is the absolute error divided by the expected probability, so it is
+    /// infinite when the expected probability is zero.
+    ///
+    /// # Examples
+    /// ```
+    /// use coin_flip_simulation;
+    ///
+    /// let result = coin_flip_simulation::run(3, 8000);
+    /// let comparison = result.comparison();
+    ///
+    /// assert_eq!(comparison.len(), 8);
+    /// assert_eq!(comparison[0].outcome, "HHH");
+    /// assert_eq!(comparison[0].expected, 0.125);
+    /// ```
+    pub fn comparison(&self) -> Vec<OutcomeComparison> {
+        self.iter_with_expected()
+            .map(|(outcome, result, expected)| {
+                let abs_error = (result.probability - expected).abs();
+
+                OutcomeComparison {
+                    outcome: outcome.clone(),
+                    observed: result.probability,
+                    expected,
+                    abs_error,
+                    rel_error: abs_error / expected,
+                }
+            })
+            .collect()
+    }
+
+    /// Iterates over each outcome along with its empirical result and expected probability.
+    ///
+    /// # Examples
+    /// ```
+    /// use coin_flip_simulation;
+    ///
+    /// let result = coin_flip_simulation::run(2, 100);
+    ///
+    /// for (outcome, observed, expected) in result.iter_with_expected() {
+    ///     println!("{outcome}: {} vs {expected}", observed.probability);
+    /// }
+    /// ```
+    pub fn iter_with_expected(&self) -> impl Iterator<Item = (&String, &EmpiricalResult, f64)> {
+        self.results
+            .iter()
+            .map(|(outcome, result)| (outcome, result, self.expected_probability_of(outcome)))
+    }
+
+    /// Iterates over each outcome in index order, as given by [`outcome_to_index_with_symbols`]
+    /// and extended to any number of symbols, rather than in the sorted order of `results`.
+    ///
+    /// This is the order [`get_all_outcomes_with_symbols`] generates outcomes in, reading each as
+    /// a number in base `symbols.len()`. The two orders only agree when the symbols are in sorted
+    /// order. Any outcome that isn't made of the result's symbols comes last.
+    ///
+    /// # Examples
+    /// ```
+    /// use coin_flip_simulation;
+    ///
+    /// let result = coin_flip_simulation::run_with_symbols(2, 100, ['1', '0']);
+    /// let outcomes: Vec<&String> = result.iter_by_index().map(|(outcome, _)| outcome).collect();
+    ///
+    /// assert_eq!(outcomes, ["11", "10", "01", "00"]);
+    /// ```
+    pub fn iter_by_index(&self) -> impl Iterator<Item = (&String, &EmpiricalResult)> {
+        let mut entries: Vec<_> = self.results.iter().collect();
+        entries.sort_by_key(|(outcome, _)| {
+            outcome_index_over_symbols(outcome, &self.symbols).map_or((1, 0), |index| (0, index))
+        });
+
+        entries.into_iter()
+    }
+
+    /// Scales each outcome's count to what it would be over `target_iterations` iterations.
+    ///
+    /// # Examples
+    /// ```
+    /// use coin_flip_simulation;
+    ///
+    /// let result = coin_flip_simulation::run(3, 5000);
+    /// let rescaled = result.rescale_counts(10000);
+    ///
+    /// assert_eq!(rescaled["HHH"], result.results["HHH"].probability * 10000.0);
+    /// ```
+    pub fn rescale_counts(&self, target_iterations: usize) -> BTreeMap<String, f64> {
+        self.results
+            .iter()
+            .map(|(outcome, result)| {
+                (
+                    outcome.clone(),
+                    result.probability * target_iterations as f64,
+                )
+            })
+            .collect()
+    }
+
+    /// Gets the lag-1 autocorrelation of the flips within an iteration, treating heads as 1 and
+    /// tails as 0.
+    ///
+    /// Every pair of consecutive flips in every recorded iteration is pooled into a single Pearson
+    /// correlation, so a fair coin gives a value near zero. The result is `NaN` when there are
+    /// fewer than two flips per iteration or when every flip is the same.
+    ///
+    /// # Examples
+    /// ```
+    /// use coin_flip_simulation;
+    ///
+    /// let result = coin_flip_simulation::run(8, 8000);
+    ///
+    /// assert!(result.lag1_autocorrelation().abs() < 0.1);
+    /// ```
+    pub fn lag1_autocorrelation(&self) -> f64 {
+        let (mut n, mut sum_x, mut sum_y, mut sum_xy, mut sum_xx, mut sum_yy) =
+            (0.0, 0.0, 0.0, 0.0, 0.0, 0.0);
+
+        for (outcome, result) in self.results.iter() {
+            let weight = result.count as f64;
+            let values: Vec<f64> = outcome
+                .chars()
+                .map(|c| if c == self.symbols[0] { 1.0 } else { 0.0 })
+                .collect();
+
+            for pair in values.windows(2) {
+                let (x, y) = (pair[0], pair[1]);
+                n += weight;
+                sum_x += weight * x;
+                sum_y += weight * y;
+                sum_xy += weight * x * y;
+                sum_xx += weight * x * x;
+                sum_yy += weight * y * y;
+            }
+        }
+
+        let covariance = sum_xy / n - (sum_x / n) * (sum_y / n);
+        let variance_x = sum_xx / n - (sum_x / n).powi(2);
+        let variance_y = sum_yy / n - (sum_y / n).powi(2);
+        covariance / (variance_x * variance_y).sqrt()
+    }
+
+    /// Gets the `n` outcomes with the highest counts, sorted by descending count and then by
+    /// outcome.
+    ///
+    /// If `n` is larger than the number of outcomes, every outcome is returned.
+    ///
+    /// # Examples
+    /// ```
+    /// use coin_flip_simulation;
+    ///
+    /// let result = coin_flip_simulation::run(3, 8000);
+    /// let top = result.top_n(2);
+    ///
+    /// assert_eq!(top.len(), 2);
+    /// assert!(top[0].1.count >= top[1].1.count);
+    /// ```
+    pub fn top_n(&self, n: usize) -> Vec<(&String, &EmpiricalResult)> {
+        let mut entries: Vec<(&String, &EmpiricalResult)> = self.results.iter().collect();
+        entries.sort_by(|(k1, v1), (k2, v2)| v2.count.cmp(&v1.count).then_with(|| k1.cmp(k2)));
+        entries.truncate(n);
+        entries
+    }
+
+    /// Gets how concentrated the outcomes are, from 0 for a perfectly uniform result to 1 when a
+    /// single outcome has every count.
+    ///
+    /// This is the Gini coefficient of the outcome probabilities normalized by its maximum:
+    /// `sum_i sum_j |p_i - p_j| / (2 * (n - 1) * sum_i p_i)`, where `n` is the number of
+    /// outcomes. A result with a single outcome has an imbalance of 0.
+    ///
+    /// # Examples
+    /// ```
+    /// use coin_flip_simulation;
+    ///
+    /// let result = coin_flip_simulation::run(3, 80000);
+    ///
+    /// assert!(result.imbalance() < 0.05);
+    /// ```
+    pub fn imbalance(&self) -> f64 {
+        let n = self.results.len();
+        if n < 2 {
+            return 0.0;
+        }
+
+        let mut probabilities: Vec<f64> = self.results.values().map(|r| r.probability).collect();
+        probabilities.sort_by(f64::total_cmp);
+
+        // With the probabilities sorted, the sum of |p_i - p_j| over every pair i < j weights
+        // each p_i by the number of smaller values minus the number of larger values.
+        let pair_sum: f64 = probabilities
+            .iter()
+            .enumerate()
+            .map(|(i, p)| p * (2.0 * i as f64 - (n - 1) as f64))
+            .sum();
+        let total: f64 = probabilities.iter().sum();
+
+        pair_sum / ((n - 1) as f64 * total)
+    }
+
+    /// Gets Pearson's chi-square statistic of the observed counts against the expected counts.
+    ///
+    /// # Examples
+    /// ```
+    /// use coin_flip_simulation;
+    ///
+    /// let result = coin_flip_simulation::run(3, 8000);
+    ///
+    /// assert!(result.chi_square() >= 0.0);
+    /// ```
+    pub fn chi_square(&self) -> f64 {
+        self.iter_with_expected()
+            .map(|(_, result, p)| {
+                let expected = p * self.iterations as f64;
+                (result.count as f64 - expected).powi(2) / expected
+            })
+            .sum()
+    }
+
+    /// Estimates the p-value of the chi-square statistic by Monte Carlo resampling.
+    ///
+    /// Simulates `resamples` datasets of `iterations` draws from the expected distribution over
+    /// the outcomes and returns the fraction whose chi-square statistic is at least as large as the
+    /// observed one. Unlike the chi-square distribution, this stays accurate at small counts.
+    /// Each statistic sums over the same outcomes as [`Self::chi_square`], so outcomes pruned from
+    /// `results` are still drawn but aren't counted. With no resamples there's nothing to compare
+    /// against, and expected probabilities that can't be sampled from, such as all zeros, have no
+    /// distribution to draw from, so either way the p-value is NaN.
+    ///
+    /// # Examples
+    /// ```
+    /// use coin_flip_simulation;
+    ///
+    /// let mut rng = rand::thread_rng();
+    /// let result = coin_flip_simulation::run(2, 1000);
+    /// let p_value = result.monte_carlo_pvalue(200, &mut rng);
+    ///
+    /// assert!((0.0..=1.0).contains(&p_value));
+    /// ```
+    pub fn monte_carlo_pvalue<R: Rng>(&self, resamples: usize, rng: &mut R) -> f64 {
+        let observed = self.chi_square();
+        // Each cell's expected probability, and whether its outcome is in `results`. With uniform
+        // probabilities the cells are interchangeable, so the first `results.len()` stand in for
+        // the observed outcomes.
+        let cells: Vec<(f64, bool)> = match &self.expected_by_outcome {
+            Some(expected_by_outcome) => expected_by_outcome
+                .iter()
+                .map(|(outcome, &p)| (p, self.results.contains_key(outcome)))
+                .collect(),
+            None => (0..self.num_outcomes())
+                .map(|i| (self.expected_probability, i < self.results.len()))
+                .collect(),
+        };
+        let sampler = match self.expected_by_outcome {
+            Some(_) => match WeightedIndex::new(cells.iter().map(|&(p, _)| p)) {
+                Ok(sampler) => Some(sampler),
+                Err(_) => return f64::NAN,
+            },
+            None => None,
+        };
+
+        let mut counts = vec![0usize; cells.len()];
+        let mut extreme = 0;
+        for _ in 0..resamples {
+            counts.iter_mut().for_each(|count| *count = 0);
+            for _ in 0..self.iterations {
+                let index = match &sampler {
+                    Some(sampler) => sampler.sample(rng),
+                    None => rng.gen_range(0..cells.len()),
+                };
+                counts[index] += 1;
+            }
+
+            let statistic: f64 = counts
+                .iter()
+                .zip(&cells)
+                .filter(|(_, &(_, observed))| observed)
+                .map(|(&count, &(p, _))| {
+                    let expected = p * self.iterations as f64;
+                    (count as f64 - expected).powi(2) / expected
+                })
+                .sum();
+            if statistic >= observed {
+                extreme += 1;
+            }
+        }
+
+        extreme as f64 / resamples as f64
+    }
+
+    /// Gets the outcomes in `results` that never occurred.
+    ///
+    /// This is empty for results run with [`SimulationBuilder::include_unobserved`] set to false,
+    /// since never-seen outcomes aren't in `results` at all.
+    ///
+    /// # Examples
+    /// ```
+    /// use coin_flip_simulation;
+    ///
+    /// let result = coin_flip_simulation::run(3, 1);
+    ///
+    /// assert_eq!(result.unobserved().len(), 7);
+    /// ```
+    pub fn unobserved(&self) -> Vec<&String> {
+        self.results
+            .iter()
+            .filter(|(_, result)| result.count == 0)
+            .map(|(outcome, _)| outcome)
+            .collect()
+    }
+
+    /// Gets the number of distinct outcomes that occurred at least once.
+    ///
+    /// # Examples
+    /// ```
+    /// use coin_flip_simulation;
+    ///
+    /// let result = coin_flip_simulation::run(3, 1);
+    ///
+    /// assert_eq!(result.distinct_observed(), 1);
+    /// ```
+    pub fn distinct_observed(&self) -> usize {
+        self.results
+            .values()
+            .filter(|result| result.count > 0)
+            .count()
+    }
+
+    /// Removes the outcomes for which `pred` returns false.
+    ///
+    /// Pruned results no longer cover every possible outcome, so the counts of `results` may sum to
+    /// less than `iterations`, and methods that walk `results`, such as [`Self::chi_square`], only
+    /// see the outcomes that are left. Take any such statistics before pruning.
+    ///
+    /// # Examples
+    /// ```
+    /// use coin_flip_simulation;
+    ///
+    /// let mut result = coin_flip_simulation::run(4, 3);
+    /// result.retain(|_, r| r.count > 0);
+    ///
+    /// assert!(result.results.len() <= 3);
+    /// ```
+    pub fn retain(&mut self, pred: impl Fn(&str, &EmpiricalResult) -> bool) {
+        self.results.retain(|outcome, result| pred(outcome, result));
+    }
+
+    /// Gets the covariance between the number of heads in an outcome and `stat` of the outcome,
+    /// weighting each outcome by its empirical probability.
+    ///
+    /// With `stat` returning the number of heads this is the variance of the head count.
+    ///
+    /// # Examples
+    /// ```
+    /// use coin_flip_simulation;
+    ///
+    /// let result = coin_flip_simulation::run(3, 8000);
+    /// let covariance = result.covariance_with(|outcome| outcome.len() as f64);
+    ///
+    /// assert!(covariance.abs() < 1e-9);
+    /// ```
+    pub fn covariance_with(&self, stat: impl Fn(&str) -> f64) -> f64 {
+        let (mut mean_heads, mut mean_stat, mut mean_product) = (0.0, 0.0, 0.0);
+
+        for (outcome, result) in self.results.iter() {
+            let heads = self.heads_in(outcome) as f64;
+            let value = stat(outcome);
+
+            mean_heads += result.probability * heads;
+            mean_stat += result.probability * value;
+            mean_product += result.probability * heads * value;
+        }
+
+        mean_product - mean_heads * mean_stat
+    }
+
+    /// Gets the mean of the two results' empirical probabilities for each outcome, weighting both
+    /// runs equally regardless of their number of iterations.
+    ///
+    /// This differs from summing counts with [`ResultAccumulator::add_result`], which weights each
+    /// run by its number of iterations. An outcome missing from one of the results counts as
+    /// probability 0 there. Returns a [`MergeError`] if the results were run with a different
+    /// number of flips per iteration.
+    ///
+    /// # Examples
+    /// ```
+    /// use coin_flip_simulation;
+    ///
+    /// let small = coin_flip_simulation::run(2, 100);
+    /// let large = coin_flip_simulation::run(2, 10000);
+    /// let average = small.average_with(&large).unwrap();
+    ///
+    /// assert!((average.values().sum::<f64>() - 1.0).abs() < 1e-9);
+    /// ```
+    pub fn average_with(
+        &self,
+        other: &CoinFlipResult,
+    ) -> Result<BTreeMap<String, f64>, MergeError> {
+        if other.flips_per_iteration != self.flips_per_iteration {
+            return Err(MergeError::FlipsMismatch {
+                expected: self.flips_per_iteration,
+                found: other.flips_per_iteration,
+            });
+        }
+
+        let mut average = BTreeMap::new();
+        for result in [self, other] {
+            for (outcome, empirical) in result.results.iter() {
+                *average.entry(outcome.clone()).or_insert(0.0) += empirical.probability / 2.0;
+            }
+        }
+
+        Ok(average)
+    }
+
+    /// Checks that the counts sum to `iterations` and the empirical probabilities sum to 1.
+    ///
+    /// Results pruned with [`Self::retain`] generally fail this check.
+    ///
+    /// # Examples
+    /// ```
+    /// use coin_flip_simulation;
+    ///
+    /// assert!(coin_flip_simulation::run(3, 8000).validate().is_ok());
+    /// ```
+    pub fn validate(&self) -> Result<(), String> {
+        let total_count: usize = self.results.values().map(|result| result.count).sum();
+        if total_count != self.iterations {
+            return Err(format!(
+                "counts sum to {total_count} but there were {} iterations",
+                self.iterations
+            ));
+        }
+
+        let total_probability: f64 = self.results.values().map(|result| result.probability).sum();
+        if self.iterations > 0 && (total_probability - 1.0).abs() > PROBABILITY_SUM_TOLERANCE {
+            return Err(format!(
+                "probabilities sum to {total_probability} instead of 1"
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Gets the mean length of the runs of identical flips in each outcome, weighted by the
+    /// outcomes' empirical probabilities.
+    ///
+    /// An outcome of `n` flips with `r` maximal runs has an average run length of `n / r`.
+    ///
+    /// # Examples
+    /// ```
+    /// use coin_flip_simulation;
+    ///
+    /// let result = coin_flip_simulation::run(1, 100);
+    ///
+    /// assert!((result.mean_run_length() - 1.0).abs() < 1e-9);
+    /// ```
+    pub fn mean_run_length(&self) -> f64 {
+        self.results
+            .iter()
+            .filter(|(outcome, _)| !outcome.is_empty())
+            .map(|(outcome, result)| {
+                let flips: Vec<char> = outcome.chars().collect();
+                let runs = 1 + flips.windows(2).filter(|pair| pair[0] != pair[1]).count();
+
+                result.probability * flips.len() as f64 / runs as f64
+            })
+            .sum()
+    }
+
+    /// Gets the mean over the outcomes of the squared difference between each outcome's empirical
+    /// and expected probabilities.
+    ///
+    /// # Examples
+    /// ```
+    /// use coin_flip_simulation;
+    ///
+    /// let result = coin_flip_simulation::run(3, 8000);
+    ///
+    /// assert!(result.mse() < 0.001);
+    /// ```
+    pub fn mse(&self) -> f64 {
+        let total: f64 = self
+            .iter_with_expected()
+            .map(|(_, observed, expected)| (observed.probability - expected).powi(2))
+            .sum();
+
+        total / self.results.len() as f64
+    }
+
+    /// Compares the empirical probabilities to a reference distribution of outcome probabilities,
+    /// such as a theoretical model, instead of the built-in expected probability.
+    ///
+    /// The KL divergence is that of the empirical distribution from the reference, and is infinite
+    /// if an outcome occurred that the reference gives probability 0. Returns a [`SimError`] naming
+    /// the first outcome that's in only one of `results` and `reference`.
+    ///
+    /// # Examples
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use coin_flip_simulation;
+    ///
+    /// let result = coin_flip_simulation::run(1, 1000);
+    /// let reference = BTreeMap::from([("H".to_string(), 0.5), ("T".to_string(), 0.5)]);
+    /// let stats = result.compare_to_reference(&reference).unwrap();
+    ///
+    /// assert!(stats.total_variation < 0.1);
+    /// ```
+    pub fn compare_to_reference(
+        &self,
+        reference: &BTreeMap<String, f64>,
+    ) -> Result<ComparisonStats, SimError> {
+        if let Some(outcome) = self
+            .results
+            .keys()
+            .find(|outcome| !reference.contains_key(*outcome))
+            .or_else(|| {
+                reference
+                    .keys()
+                    .find(|outcome| !self.results.contains_key(*outcome))
+            })
+        {
+            return Err(SimError::InvalidOutcome(outcome.clone()));
+        }
+
+        let mut stats = ComparisonStats {
+            chi_square: 0.0,
+            kl_divergence: 0.0,
+            total_variation: 0.0,
+        };
+        for (outcome, result) in self.results.iter() {
+            let (observed, expected) = (result.probability, reference[outcome]);
+            let expected_count = expected * self.iterations as f64;
+
+            stats.chi_square += (result.count as f64 - expected_count).powi(2) / expected_count;
+            if observed > 0.0 {
+                stats.kl_divergence += observed * (observed / expected).ln();
+            }
+            stats.total_variation += (observed - expected).abs() / 2.0;
+        }
+
+        Ok(stats)
+    }
+
+    /// Gets the mean number of possibly overlapping occurrences of `pattern` in an outcome,
+    /// weighting each outcome by its empirical probability.
+    ///
+    /// This is the empirical counterpart of [`expected_substring_occurrences`].
+    ///
+    /// # Examples
+    /// ```
+    /// use coin_flip_simulation;
+    ///
+    /// let result = coin_flip_simulation::run(3, 8000);
+    ///
+    /// assert!((result.mean_substring_occurrences("HH") - 0.5).abs() < 0.05);
+    /// ```
+    pub fn mean_substring_occurrences(&self, pattern: &str) -> f64 {
+        let pattern: Vec<char> = pattern.chars().collect();
+        if pattern.is_empty() {
+            return 0.0;
+        }
+
+        self.results
+            .iter()
+            .map(|(outcome, result)| {
+                let flips: Vec<char> = outcome.chars().collect();
+                let occurrences = flips
+                    .windows(pattern.len())
+                    .filter(|window| *window == pattern)
+                    .count();
+
+                result.probability * occurrences as f64
+            })
+            .sum()
+    }
+
+    /// Gets the outcomes whose counts are significantly different from the expected count at
+    /// significance level `alpha`.
+    ///
+    /// Each outcome's count is compared with a two-sided test using the normal approximation to
+    /// the binomial, and `alpha` is divided by the number of outcomes (a Bonferroni correction) so
+    /// that the chance of flagging any outcome of a fair coin stays at most `alpha`.
+    ///
+    /// # Examples
+    /// ```
+    /// use coin_flip_simulation;
+    ///
+    /// let result = coin_flip_simulation::run(3, 8000);
+    ///
+    /// assert!(result.anomalies(1e-6).is_empty());
+    /// ```
+    pub fn anomalies(&self, alpha: f64) -> Vec<&String> {
+        let n = self.iterations as f64;
+        let critical_z = normal_quantile(1.0 - alpha / (2.0 * self.results.len() as f64));
+
+        self.iter_with_expected()
+            .filter(|(_, observed, expected)| {
+                let standard_deviation = (n * expected * (1.0 - expected)).sqrt();
+                let z = (observed.count as f64 - n * expected) / standard_deviation;
+
+                z.abs() > critical_z
+            })
+            .map(|(outcome, _, _)| outcome)
+            .collect()
+    }
+
+    /// Gets the scalar statistics of a result in a single pass over its outcomes.
+    ///
+    /// # Examples
+    /// ```
+    /// use coin_flip_simulation;
+    ///
+    /// let result = coin_flip_simulation::run(3, 8000);
+    /// let summary = result.summary();
+    ///
+    /// assert_eq!(summary.chi_square, result.chi_square());
+    /// assert!((summary.mean_heads - 1.5).abs() < 0.1);
+    /// ```
+    pub fn summary(&self) -> SimSummary {
+        let n = self.iterations as f64;
+        let (mut chi_square, mut entropy, mut kl_divergence, mut squared_error) =
+            (0.0, 0.0, 0.0, 0.0);
+        let (mut mean_heads, mut mean_squared_heads) = (0.0, 0.0);
+
+        for (outcome, observed, expected) in self.iter_with_expected() {
+            let p = observed.probability;
+            let heads = self.heads_in(outcome) as f64;
+
+            chi_square += (observed.count as f64 - n * expected).powi(2) / (n * expected);
+            if p > 0.0 {
+                entropy -= p * p.log2();
+                kl_divergence += p * (p / expected).ln();
+            }
+            squared_error += (p - expected).powi(2);
+            mean_heads += p * heads;
+            mean_squared_heads += p * heads * heads;
+        }
+
+        SimSummary {
+            chi_square,
+            entropy,
+            kl_divergence,
+            mse: squared_error / self.results.len() as f64,
+            mean_heads,
+            heads_variance: mean_squared_heads - mean_heads * mean_heads,
+        }
+    }
+
+    /// Gets the Kolmogorov–Smirnov statistic of the number of heads per iteration against the
+    /// binomial distribution with probability `p_heads` of heads.
+    ///
+    /// This is the largest absolute difference between the empirical and binomial cumulative
+    /// probabilities of at most `k` heads, over `k` from 0 to `flips_per_iteration`.
+    ///
+    /// # Examples
+    /// ```
+    /// use coin_flip_simulation;
+    ///
+    /// let result = coin_flip_simulation::run(4, 8000);
+    ///
+    /// assert!(result.ks_statistic(0.5) < 0.05);
+    /// assert!(result.ks_statistic(0.9) > 0.5);
+    /// ```
+    pub fn ks_statistic(&self, p_heads: f64) -> f64 {
+        let n = self.flips_per_iteration;
+        let mut empirical = vec![0.0; n + 1];
+        for (outcome, result) in self.results.iter() {
+            empirical[self.heads_in(outcome).min(n)] += result.probability;
+        }
+
+        let mut binomial_coefficient = 1.0;
+        let (mut empirical_cdf, mut binomial_cdf, mut statistic) = (0.0, 0.0, 0.0f64);
+        for (k, probability) in empirical.into_iter().enumerate() {
+            if k > 0 {
+                binomial_coefficient *= (n - k + 1) as f64 / k as f64;
+            }
+
+            empirical_cdf += probability;
+            let tails = n - k;
+            binomial_cdf +=
+                binomial_coefficient * p_heads.powi(k as i32) * (1.0 - p_heads).powi(tails as i32);
+            statistic = statistic.max((empirical_cdf - binomial_cdf).abs());
+        }
+
+        statistic
+    }
+
+    /// Counts the outcomes that are over-represented, on target and under-represented, returned in
+    /// that order.
+    ///
+    /// An outcome is on target when its empirical probability is within `rel_tol` of its expected
+    /// probability, relative to the expected probability.
+    ///
+    /// # Examples
+    /// ```
+    /// use coin_flip_simulation;
+    ///
+    /// let result = coin_flip_simulation::run(3, 8000);
+    /// let (over, on_target, under) = result.bucket_summary(0.2);
+    ///
+    /// assert_eq!(over + on_target + under, 8);
+    /// ```
+    pub fn bucket_summary(&self, rel_tol: f64) -> (usize, usize, usize) {
+        let (mut over, mut on_target, mut under) = (0, 0, 0);
+        for (_, observed, expected) in self.iter_with_expected() {
+            let rel_error = (observed.probability - expected) / expected;
+
+            if rel_error.abs() <= rel_tol {
+                on_target += 1;
+            } else if rel_error > 0.0 {
+                over += 1;
+            } else {
+                under += 1;
+            }
+        }
+
+        (over, on_target, under)
+    }
+
+    /// Gets the empirical frequency of heads at each flip position, weighting each outcome by its
+    /// probability.
+    ///
+    /// For a fair coin every position should be near 0.5, so a position that stands out points to
+    /// a position-specific bias.
+    ///
+    /// # Examples
+    /// ```
+    /// use coin_flip_simulation;
+    ///
+    /// let result = coin_flip_simulation::run(3, 8000);
+    /// let frequencies = result.per_position_head_frequency();
+    ///
+    /// assert_eq!(frequencies.len(), 3);
+    /// assert!(frequencies.iter().all(|f| (f - 0.5).abs() < 0.05));
+    /// ```
+    pub fn per_position_head_frequency(&self) -> Vec<f64> {
+        let mut frequencies = vec![0.0; self.flips_per_iteration];
+        for (outcome, result) in self.results.iter() {
+            for (frequency, c) in frequencies.iter_mut().zip(outcome.chars()) {
+                if c == self.symbols[0] {
+                    *frequency += result.probability;
+                }
+            }
+        }
+
+        frequencies
+    }
+
+    /// Resamples a new result by drawing `iterations` outcomes with replacement, treating the
+    /// observed counts as the population.
+    ///
+    /// Repeating this and recomputing a statistic on each resample estimates that statistic's
+    /// sampling variability. The resample keeps the same outcomes, symbols and expected
+    /// probabilities as this result.
+    ///
+    /// # Examples
+    /// ```
+    /// use coin_flip_simulation;
+    ///
+    /// let mut rng = rand::thread_rng();
+    /// let result = coin_flip_simulation::run(3, 1000);
+    /// let resampled = result.bootstrap(&mut rng);
+    ///
+    /// assert_eq!(resampled.iterations, 1000);
+    /// assert_eq!(resampled.results.len(), 8);
+    /// ```
+    pub fn bootstrap<R: Rng>(&self, rng: &mut R) -> CoinFlipResult {
+        let mut resampled = self.clone();
+        let Ok(sampler) = WeightedIndex::new(self.results.values().map(|result| result.count))
+        else {
+            return resampled;
+        };
+
+        let mut counts = vec![0; self.results.len()];
+        for _ in 0..self.iterations {
+            counts[sampler.sample(rng)] += 1;
+        }
+
+        for (result, count) in resampled.results.values_mut().zip(counts) {
+            *result = EmpiricalResult::new(count, self.iterations);
+        }
+
+        resampled
+    }
+
+    /// Gets the expected value of a single play of a game that pays out `payouts` for each
+    /// outcome, weighted by the empirical probabilities.
+    ///
+    /// Outcomes missing from `payouts` pay out nothing, and negative payouts are losses.
+    ///
+    /// # Examples
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use coin_flip_simulation;
+    ///
+    /// let result = coin_flip_simulation::run(3, 8000);
+    /// let payouts = BTreeMap::from([("HHH".to_string(), 8.0)]);
+    ///
+    /// assert!((result.expected_value(&payouts) - 1.0).abs() < 0.25);
+    /// ```
+    pub fn expected_value(&self, payouts: &BTreeMap<String, f64>) -> f64 {
+        self.results
+            .iter()
+            .map(|(outcome, result)| {
+                payouts
+                    .get(outcome)
+                    .map_or(0.0, |payout| payout * result.probability)
+            })
+            .sum()
+    }
+
+    /// Gets the empirical probability of each Hamming distance `0..=flips_per_iteration` between
+    /// an outcome and `reference`, which is the number of positions where they differ.
+    ///
+    /// For a fair coin, the distances follow the binomial distribution with
+    /// `flips_per_iteration` trials and probability 0.5 whatever the reference.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `reference` doesn't have `flips_per_iteration` symbols.
+    ///
+    /// # Examples
+    /// ```
+    /// use coin_flip_simulation;
+    ///
+    /// let result = coin_flip_simulation::run(3, 8000);
+    /// let distances = result.hamming_distance_distribution("HHH");
+    ///
+    /// assert_eq!(distances.len(), 4);
+    /// assert!((distances[&1] - 0.375).abs() < 0.05);
+    /// ```
+    pub fn hamming_distance_distribution(&self, reference: &str) -> BTreeMap<usize, f64> {
+        assert_eq!(
+            reference.chars().count(),
+            self.flips_per_iteration,
+            "{reference:?} doesn't have {} symbols",
+            self.flips_per_iteration,
+        );
+
+        let mut distribution: BTreeMap<usize, f64> = (0..=self.flips_per_iteration)
+            .map(|distance| (distance, 0.0))
+            .collect();
+        for (outcome, result) in self.results.iter() {
+            let distance = outcome
+                .chars()
+                .zip(reference.chars())
+                .filter(|(a, b)| a != b)
+                .count();
+            *distribution.entry(distance).or_insert(0.0) += result.probability;
+        }
+
+        distribution
+    }
+
+    /// Gets the empirical probability that an outcome contains a run of at least `length`
+    /// consecutive `symbol`s, or of either symbol when `symbol` is `None`.
+    ///
+    /// # Examples
+    /// ```
+    /// use coin_flip_simulation;
+    ///
+    /// let result = coin_flip_simulation::run(3, 8000);
+    ///
+    /// assert!((result.probability_of_run(3, None) - 0.25).abs() < 0.05);
+    /// assert!((result.probability_of_run(2, Some('H')) - 0.375).abs() < 0.05);
+    /// ```
+    pub fn probability_of_run(&self, length: usize, symbol: Option<char>) -> f64 {
+        let has_run = |outcome: &str| {
+            let (mut previous, mut run_length) = (None, 0);
+            for c in outcome.chars() {
+                run_length = if previous == Some(c) {
+                    run_length + 1
+                } else {
+                    1
+                };
+                previous = Some(c);
+
+                if run_length >= length && symbol.is_none_or(|symbol| symbol == c) {
+                    return true;
+                }
+            }
+
+            length == 0
+        };
+
+        self.results
+            .iter()
+            .filter(|(outcome, _)| has_run(outcome))
+            .map(|(_, result)| result.probability)
+            .sum()
+    }
+
+    /// Draws an outcome at random according to the empirical probabilities, so more frequently
+    /// observed outcomes are drawn more often.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `results` is empty.
+    ///
+    /// # Examples
+    /// ```
+    /// use coin_flip_simulation;
+    ///
+    /// let mut rng = rand::thread_rng();
+    /// let result = coin_flip_simulation::run(3, 100);
+    /// let outcome = result.sample(&mut rng);
+    ///
+    /// assert!(result.results[outcome].count > 0);
+    /// ```
+    pub fn sample<R: Rng>(&self, rng: &mut R) -> &String {
+        let target: f64 = rng.gen();
+
+        let mut cumulative = 0.0;
+        for (outcome, result) in self.results.iter() {
+            cumulative += result.probability;
+            if cumulative > target {
+                return outcome;
+            }
+        }
+
+        // Rounding can leave the cumulative probability just short of 1.
+        self.results
+            .iter()
+            .rev()
+            .find(|(_, result)| result.count > 0)
+            .or_else(|| self.results.iter().next_back())
+            .map(|(outcome, _)| outcome)
+            .expect("results is empty")
+    }
+
+    /// Gets the effective sample size of the iterations when consecutive iterations have lag-1
+    /// autocorrelation `rho`, as in [`run_markov`], which is `n * (1 - rho) / (1 + rho)`.
+    ///
+    /// Positive autocorrelation makes the iterations worth fewer independent ones and negative
+    /// autocorrelation makes them worth more. Returns NaN unless `-1 < rho <= 1`.
+    ///
+    /// # Examples
+    /// ```
+    /// use coin_flip_simulation;
+    ///
+    /// let result = coin_flip_simulation::run(3, 1000);
+    ///
+    /// assert_eq!(result.effective_sample_size(0.5), 1000.0 / 3.0);
+    /// ```
+    pub fn effective_sample_size(&self, rho: f64) -> f64 {
+        if !(rho > -1.0 && rho <= 1.0) {
+            return f64::NAN;
+        }
+
+        self.iterations as f64 * (1.0 - rho) / (1.0 + rho)
+    }
+
+    /// Gets Pearson's chi-square statistic of the two-row contingency table of this result's
+    /// counts against `other`'s, testing whether both runs come from the same distribution.
+    ///
+    /// The statistic has one degree of freedom fewer than the number of outcomes, and outcomes
+    /// that neither run observed are left out. Returns a [`MergeError`] if the results were run
+    /// with a different number of flips per iteration or don't have the same outcomes.
+    ///
+    /// # Examples
+    /// ```
+    /// use coin_flip_simulation;
+    ///
+    /// let a = coin_flip_simulation::run(2, 8000);
+    /// let b = coin_flip_simulation::run(2, 8000);
+    /// let statistic = a.two_sample_chi_square(&b).unwrap();
+    ///
+    /// assert!(statistic >= 0.0);
+    /// ```
+    pub fn two_sample_chi_square(&self, other: &CoinFlipResult) -> Result<f64, MergeError> {
+        if other.flips_per_iteration != self.flips_per_iteration {
+            return Err(MergeError::FlipsMismatch {
+                expected: self.flips_per_iteration,
+                found: other.flips_per_iteration,
+            });
+        }
+        if let Some(outcome) = self
+            .results
+            .keys()
+            .find(|outcome| !other.results.contains_key(*outcome))
+            .or_else(|| {
+                other
+                    .results
+                    .keys()
+                    .find(|outcome| !self.results.contains_key(*outcome))
+            })
+        {
+            return Err(MergeError::OutcomeMismatch(outcome.clone()));
+        }
+
+        let row_totals = [self.iterations as f64, other.iterations as f64];
+        let total = row_totals[0] + row_totals[1];
+
+        let mut statistic = 0.0;
+        for (outcome, a) in self.results.iter() {
+            let counts = [a.count as f64, other.results[outcome].count as f64];
+            let column_total = counts[0] + counts[1];
+
+            for (count, row_total) in counts.iter().zip(row_totals) {
+                let expected = row_total * column_total / total;
+                if expected > 0.0 {
+                    statistic += (count - expected).powi(2) / expected;
+                }
+            }
+        }
+
+        Ok(statistic)
+    }
+
+    /// Encodes a CoinFlipResult into a compact binary representation.
+    ///
+    /// All integers are little-endian, and each outcome is written as its byte length as a `u32`
+    /// followed by its UTF-8 bytes. The fields are, in order:
+    ///
+    /// 1. `flips_per_iteration`, `iterations`, the expected count and the number of possible
+    ///    outcomes, each as a `u64`.
+    /// 2. The number of symbols as a `u32`, followed by each symbol as a `u32`.
+    /// 3. The number of results as a `u64`, followed by each result's outcome and its count as a
+    ///    `u64`. Probabilities are recomputed from the counts, so they aren't stored.
+    /// 4. A `u8` flag that is 1 if the outcomes have their own expected probabilities, such as
+    ///    those set by [`Self::set_expected_map`], or 0 otherwise. When it's 1, it's followed by
+    ///    the number of them as a `u64` and each outcome with the bits of its expected
+    ///    probability as a `u64`.
+    ///
+    /// # Examples
+    /// ```
+    /// use coin_flip_simulation::{self, CoinFlipResult};
+    ///
+    /// let result = coin_flip_simulation::run(3, 8000);
+    /// let bytes = result.to_bytes();
+    /// let decoded = CoinFlipResult::from_bytes(&bytes).unwrap();
+    ///
+    /// assert_eq!(decoded.iterations, 8000);
+    /// assert_eq!(decoded.to_bytes(), bytes);
+    /// ```
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(&(self.flips_per_iteration as u64).to_le_bytes());
+        bytes.extend_from_slice(&(self.iterations as u64).to_le_bytes());
+        bytes.extend_from_slice(&(self.expected.count as u64).to_le_bytes());
+        bytes.extend_from_slice(&(self.num_outcomes as u64).to_le_bytes());
+
+        bytes.extend_from_slice(&(self.symbols.len() as u32).to_le_bytes());
+        for &symbol in self.symbols.iter() {
+            bytes.extend_from_slice(&u32::from(symbol).to_le_bytes());
+        }
+
+        bytes.extend_from_slice(&(self.results.len() as u64).to_le_bytes());
+        for (k, v) in self.results.iter() {
+            bytes.extend_from_slice(&(k.len() as u32).to_le_bytes());
+            bytes.extend_from_slice(k.as_bytes());
+            bytes.extend_from_slice(&(v.count as u64).to_le_bytes());
+        }
+
+        match &self.expected_by_outcome {
+            Some(expected_by_outcome) => {
+                bytes.push(1);
+                bytes.extend_from_slice(&(expected_by_outcome.len() as u64).to_le_bytes());
+                for (k, p) in expected_by_outcome.iter() {
+                    bytes.extend_from_slice(&(k.len() as u32).to_le_bytes());
+                    bytes.extend_from_slice(k.as_bytes());
+                    bytes.extend_from_slice(&p.to_bits().to_le_bytes());
+                }
+            }
+            None => bytes.push(0),
+        }
+
+        bytes
+    }
+
+    /// Decodes a CoinFlipResult from the binary representation produced by [`Self::to_bytes`].
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let mut reader = ByteReader { bytes };
+
+        let flips_per_iteration = reader.read_usize()?;
+        let iterations = reader.read_usize()?;
+        let expected = EmpiricalResult::new(reader.read_usize()?, iterations);
+        let num_outcomes = reader.read_usize()?;
+        if num_outcomes == 0 {
+            return Err(DecodeError::NoOutcomes);
+        }
+
+        let num_symbols = reader.read_u32()?;
+        let mut symbols = Vec::new();
+        for _ in 0..num_symbols {
+            let symbol = char::from_u32(reader.read_u32()?).ok_or(DecodeError::InvalidSymbol)?;
+            symbols.push(symbol);
+        }
+        if symbols.len() < 2
+            || symbols
+                .iter()
+                .enumerate()
+                .any(|(i, s)| symbols[..i].contains(s))
+        {
+            return Err(DecodeError::InvalidSymbols);
+        }
+
+        let num_results = reader.read_usize()?;
+        let mut results = BTreeMap::new();
+        for _ in 0..num_results {
+            let key = reader.read_string()?;
+            let count = reader.read_usize()?;
+            results.insert(key, EmpiricalResult::new(count, iterations));
+        }
+
+        let expected_by_outcome = match reader.read_u8()? {
+            0 => None,
+            1 => {
+                let num_expected = reader.read_usize()?;
+                let mut expected_by_outcome = BTreeMap::new();
+                for _ in 0..num_expected {
+                    let key = reader.read_string()?;
+                    expected_by_outcome.insert(key, f64::from_bits(reader.read_u64()?));
+                }
+                Some(expected_by_outcome)
+            }
+            _ => return Err(DecodeError::InvalidFlag),
+        };
+
+        if !reader.bytes.is_empty() {
+            return Err(DecodeError::TrailingBytes);
+        }
+
+        let mut result = CoinFlipResult::new(
+            flips_per_iteration,
+            iterations,
+            expected,
+            num_outcomes,
+            results,
+        );
+        result.symbols = symbols;
+        result.expected_by_outcome = expected_by_outcome;
+        Ok(result)
+    }
+
+    /// Converts a CoinFlipResult to a json string.
+    pub fn to_json_string(&self) -> String {
+        self.render_json(self.results.iter(), DEFAULT_PRECISION)
+    }
+
+    /// Estimates the length in bytes of [`Self::to_json_string`] without building it.
+    ///
+    /// The estimate sums the length of each outcome, its count's digits and a fixed overhead per
+    /// entry, assuming probabilities render as `0.xxxxx`. It's exact unless `iterations` is 0.
+    ///
+    /// # Examples
+    /// ```
+    /// use coin_flip_simulation;
+    ///
+    /// let result = coin_flip_simulation::run(4, 1000);
+    ///
+    /// assert_eq!(result.estimated_json_len(), result.to_json_string().len());
+    /// ```
+    pub fn estimated_json_len(&self) -> usize {
+        let digits = |n: usize| n.checked_ilog10().unwrap_or(0) as usize + 1;
+        let probability_len = 2 + DEFAULT_PRECISION;
+        let entry_len =
+            |count: usize| "{count: , probability: }".len() + digits(count) + probability_len;
+
+        let header = "{\n    iterations: \n    expected: \n    actual: {\n".len()
+            + digits(self.iterations)
+            + entry_len(self.expected.count);
+        let footer = "    }\n}".len();
+        let entries: usize = self
+            .results
+            .iter()
+            .map(|(k, v)| "        : \n".len() + k.len() + entry_len(v.count))
+            .sum();
+
+        header + entries + footer
+    }
+
+    /// Converts a CoinFlipResult to a json string with outcomes in the order they're generated by
+    /// [`get_all_outcomes_with_symbols`] rather than in sorted order.
+    ///
+    /// The two orders coincide for `H` and `T`, but not for symbols where heads sorts after
+    /// tails, such as `1` and `0`. Results with more than two symbols, such as those of
+    /// [`run_with_edge`], are ordered the same way as [`Self::iter_by_index`].
+    ///
+    /// # Examples
+    /// ```
+    /// use coin_flip_simulation;
+    ///
+    /// let json = coin_flip_simulation::run_with_symbols(1, 10, ['1', '0'])
+    ///     .to_json_string_in_generation_order();
+    ///
+    /// assert!(json.find("1:").unwrap() < json.find("0:").unwrap());
+    /// ```
+    pub fn to_json_string_in_generation_order(&self) -> String {
+        self.render_json(self.iter_by_index(), DEFAULT_PRECISION)
+    }
+
+    /// Converts a CoinFlipResult to a json string containing only the `n` highest-count outcomes.
+    pub fn to_json_string_top_n(&self, n: usize) -> String {
+        self.render_json(self.top_n(n).into_iter(), DEFAULT_PRECISION)
+    }
+
+    /// Converts a CoinFlipResult to a json string with each outcome replaced by its label in
+    /// `labels`, or left as is if it has none.
+    ///
+    /// # Examples
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use coin_flip_simulation;
+    ///
+    /// let labels = BTreeMap::from([("HHH".to_string(), "Jackpot".to_string())]);
+    /// let json = coin_flip_simulation::run(3, 100).to_json_string_with_labels(&labels);
+    ///
+    /// assert!(json.contains("Jackpot: "));
+    /// assert!(json.contains("TTT: "));
+    /// ```
+    pub fn to_json_string_with_labels(&self, labels: &BTreeMap<String, String>) -> String {
+        self.render_json(
+            self.results
+                .iter()
+                .map(|(k, v)| (labels.get(k).unwrap_or(k), v)),
+            DEFAULT_PRECISION,
+        )
+    }
+
+    /// Converts a CoinFlipResult to a json string with an `expected` object of each outcome's
+    /// expected probability alongside an `actual` object of its empirical probability, keyed
+    /// identically so a json diff lines them up.
+    ///
+    /// # Examples
+    /// ```
+    /// use coin_flip_simulation;
+    ///
+    /// let json = coin_flip_simulation::run(1, 10).to_json_string_with_expected();
+    ///
+    /// assert!(json.contains("    expected: {\n        H: 0.50000\n        T: 0.50000\n    }"));
+    /// ```
+    pub fn to_json_string_with_expected(&self) -> String {
+        let indent = "    ";
+        let mut json = format!("{{\n{indent}iterations: {}\n", self.iterations);
+
+        let sections = [
+            (
+                "expected",
+                self.results
+                    .keys()
+                    .map(|k| (k, self.expected_probability_of(k)))
+                    .collect::<Vec<_>>(),
+            ),
+            (
+                "actual",
+                self.results
+                    .iter()
+                    .map(|(k, v)| (k, v.probability))
+                    .collect(),
+            ),
+        ];
+        for (name, probabilities) in sections {
+            json.push_str(&format!("{indent}{name}: {{\n"));
+            for (k, p) in probabilities {
+                json.push_str(&format!("{indent}{indent}{k}: {p:.DEFAULT_PRECISION$}\n"));
+            }
+            json.push_str(&format!("{indent}}}\n"));
+        }
+
+        json.push('}');
+        json
+    }
+
+    fn render_json<'a>(
+        &self,
+        entries: impl Iterator<Item = (&'a String, &'a EmpiricalResult)>,
+        precision: usize,
+    ) -> String {
+        let indent = "    ";
+        let mut json = format!(
+            "{{\n{indent}iterations: {}\n{indent}expected: {:.precision$}\n{indent}actual: {{\n",
+            self.iterations, self.expected,
+        );
+
+        for (k, v) in entries {
+            let entry = format!("{indent}{indent}{k}: {v:.precision$}\n");
+            json.push_str(&entry);
+        }
+
+        let close = format!("{indent}}}\n}}");
+        json.push_str(&close);
+        json
+    }
+
+    /// Writes a CoinFlipResult as CSV rows of `outcome,count,probability` with a header row.
+    ///
+    /// Rows are written one at a time, so large results can be streamed to a file without
+    /// building the whole CSV in memory.
+    ///
+    /// # Examples
+    /// ```
+    /// use coin_flip_simulation;
+    ///
+    /// let mut csv = Vec::new();
+    /// coin_flip_simulation::run(2, 100).write_csv(&mut csv).unwrap();
+    ///
+    /// assert_eq!(csv.split(|&b| b == b'\n').next(), Some(&b"outcome,count,probability"[..]));
+    /// ```
+    pub fn write_csv<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        writeln!(w, "outcome,count,probability")?;
+        for (k, v) in self.results.iter() {
+            writeln!(w, "{k},{},{}", v.count, v.probability)?;
+        }
+
+        Ok(())
+    }
+
+    /// Converts a CoinFlipResult to a CSV string, as written by [`Self::write_csv`].
+    pub fn to_csv_string(&self) -> String {
+        let mut csv = Vec::new();
+        self.write_csv(&mut csv)
+            .expect("writing to a Vec can't fail");
+
+        String::from_utf8(csv).expect("outcomes are valid UTF-8")
+    }
+
+    /// Converts a CoinFlipResult to a GitHub-flavored markdown table.
+    ///
+    /// # Examples
+    /// ```
+    /// use coin_flip_simulation;
+    ///
+    /// let table = coin_flip_simulation::run(1, 10).to_markdown_table();
+    /// let mut lines = table.lines();
+    ///
+    /// assert_eq!(lines.next(), Some("| Outcome | Count | Probability |"));
+    /// assert_eq!(lines.next(), Some("| :--- | ---: | ---: |"));
+    /// assert!(lines.next().unwrap().starts_with("| H | "));
+    /// ```
+    pub fn to_markdown_table(&self) -> String {
+        let mut table = String::from("| Outcome | Count | Probability |\n| :--- | ---: | ---: |\n");
+
+        for (k, v) in self.results.iter() {
+            let row = format!("| {k} | {} | {:.5} |\n", v.count, v.probability);
+            table.push_str(&row);
+        }
+
+        table
+    }
+
+    /// Converts a CoinFlipResult to a LaTeX `tabular` environment.
+    ///
+    /// # Examples
+    /// ```
+    /// use coin_flip_simulation;
+    ///
+    /// let table = coin_flip_simulation::run(1, 10).to_latex_table();
+    ///
+    /// assert!(table.starts_with("\\begin{tabular}{lrr}\n"));
+    /// assert!(table.ends_with("\\end{tabular}\n"));
+    /// ```
+    pub fn to_latex_table(&self) -> String {
+        let mut table = String::from(
+            "\\begin{tabular}{lrr}\n\\hline\nOutcome & Count & Probability \\\\\n\\hline\n",
+        );
+
+        for (k, v) in self.results.iter() {
+            let row = format!("{k} & {} & {:.5} \\\\\n", v.count, v.probability);
+            table.push_str(&row);
+        }
+
+        table.push_str("\\hline\n\\end{tabular}\n");
+        table
+    }
+
+    /// Converts a CoinFlipResult to `(outcome, probability)` points in sorted outcome order, the
+    /// shape bar charts in plotting libraries such as `plotters` consume.
+    ///
+    /// # Examples
+    /// ```
+    /// use coin_flip_simulation;
+    ///
+    /// let points = coin_flip_simulation::run(2, 100).to_plot_points();
+    ///
+    /// assert_eq!(points.len(), 4);
+    /// assert_eq!(points[0].0, "HH");
+    /// ```
+    pub fn to_plot_points(&self) -> Vec<(String, f64)> {
+        self.results
+            .iter()
+            .map(|(k, v)| (k.clone(), v.probability))
+            .collect()
+    }
+
+    /// Converts a CoinFlipResult to `(outcome, count)` points in sorted outcome order.
+    pub fn to_plot_points_counts(&self) -> Vec<(String, f64)> {
+        self.results
+            .iter()
+            .map(|(k, v)| (k.clone(), v.count as f64))
+            .collect()
+    }
+
+    /// Converts a CoinFlipResult to a single line of `key=value` fields for appending to a log,
+    /// such as `flips=3 iters=8000 chi2=5.2 entropy=2.99 max_err=0.004`.
+    ///
+    /// `max_err` is the largest absolute difference between an outcome's empirical and expected
+    /// probabilities.
+    ///
+    /// # Examples
+    /// ```
+    /// use coin_flip_simulation;
+    ///
+    /// let line = coin_flip_simulation::run(3, 8000).to_log_line();
+    ///
+    /// assert!(line.starts_with("flips=3 iters=8000 chi2="));
+    /// ```
+    pub fn to_log_line(&self) -> String {
+        let summary = self.summary();
+        let max_error = self
+            .iter_with_expected()
+            .map(|(_, observed, expected)| (observed.probability - expected).abs())
+            .fold(0.0, f64::max);
+
+        format!(
+            "flips={} iters={} chi2={:.1} entropy={:.2} max_err={:.3}",
+            self.flips_per_iteration,
+            self.iterations,
+            summary.chi_square,
+            summary.entropy,
+            max_error
+        )
+    }
+
+    /// Gets the outcomes, counts and probabilities as parallel columns in sorted outcome order,
+    /// ready to hand to a columnar format such as Arrow.
+    ///
+    /// # Examples
+    /// ```
+    /// use coin_flip_simulation;
+    ///
+    /// let (outcomes, counts, probabilities) = coin_flip_simulation::run(2, 100).to_columns();
+    ///
+    /// assert_eq!(outcomes, ["HH", "HT", "TH", "TT"]);
+    /// assert_eq!(counts.iter().sum::<u64>(), 100);
+    /// assert_eq!(probabilities.len(), 4);
+    /// ```
+    pub fn to_columns(&self) -> (Vec<String>, Vec<u64>, Vec<f64>) {
+        let mut outcomes = Vec::with_capacity(self.results.len());
+        let mut counts = Vec::with_capacity(self.results.len());
+        let mut probabilities = Vec::with_capacity(self.results.len());
+
+        for (outcome, result) in self.results.iter() {
+            outcomes.push(outcome.clone());
+            counts.push(result.count as u64);
+            probabilities.push(result.probability);
+        }
+
+        (outcomes, counts, probabilities)
+    }
+
+    /// Renders a bar chart of each outcome's empirical probability to a PNG at `path`.
+    ///
+    /// The bars are in the order of `results`. The chart only has a caption and axis labels with
+    /// the `plot-labels` feature, since `plotters` needs a system font to draw text.
+    ///
+    /// Results with many outcomes produce unreadably thin bars; use [`Self::save_bar_chart_top_n`]
+    /// to only chart the most common ones.
+    #[cfg(feature = "plotters")]
+    pub fn save_bar_chart(&self, path: &str) -> Result<(), Box<dyn Error>> {
+        self.draw_bar_chart(path, self.results.iter().collect())
+    }
+
+    /// Renders a bar chart of the empirical probabilities of the `n` highest-count outcomes, as
+    /// given by [`Self::top_n`], to a PNG at `path`.
+    #[cfg(feature = "plotters")]
+    pub fn save_bar_chart_top_n(&self, path: &str, n: usize) -> Result<(), Box<dyn Error>> {
+        self.draw_bar_chart(path, self.top_n(n))
+    }
+
+    #[cfg(feature = "plotters")]
+    fn draw_bar_chart(
+        &self,
+        path: &str,
+        entries: Vec<(&String, &EmpiricalResult)>,
+    ) -> Result<(), Box<dyn Error>> {
+        use plotters::prelude::*;
+
+        let max_probability = entries
+            .iter()
+            .map(|(_, result)| result.probability)
+            .chain(self.iter_with_expected().map(|(_, _, p)| p))
+            .fold(0.0, f64::max);
+
+        let root = BitMapBackend::new(path, (800, 600)).into_drawing_area();
+        root.fill(&WHITE)?;
+
+        // Without a font there's no text to draw, so the label areas are left at zero size and
+        // the axes go unlabeled.
+        let mut builder = ChartBuilder::on(&root);
+        builder.margin(10);
+        #[cfg(feature = "plot-labels")]
+        builder
+            .caption(
+                format!(
+                    "{} flips per iteration, {} iterations",
+                    self.flips_per_iteration, self.iterations
+                ),
+                ("sans-serif", 24),
+            )
+            .x_label_area_size(40)
+            .y_label_area_size(60);
+        let mut chart = builder.build_cartesian_2d(
+            (0..entries.len()).into_segmented(),
+            0.0..max_probability * 1.1,
+        )?;
+
+        #[cfg(feature = "plot-labels")]
+        let outcome_label = |segment: &SegmentValue<usize>| match segment {
+            SegmentValue::CenterOf(i) => entries
+                .get(*i)
+                .map_or(String::new(), |(k, _)| k.to_string()),
+            _ => String::new(),
+        };
+        let mut mesh = chart.configure_mesh();
+        mesh.disable_x_mesh();
+        #[cfg(feature = "plot-labels")]
+        mesh.x_desc("Outcome")
+            .y_desc("Probability")
+            .x_label_formatter(&outcome_label);
+        mesh.draw()?;
+
+        chart.draw_series(
+            Histogram::vertical(&chart)
+                .style(BLUE.filled())
+                .margin(4)
+                .data(
+                    entries
+                        .iter()
+                        .enumerate()
+                        .map(|(i, (_, result))| (i, result.probability)),
+                ),
+        )?;
+
+        root.present()?;
+        Ok(())
+    }
+}
+
+/// Formats a CoinFlipResult as a json string, with probabilities rounded to the formatter's
+/// precision (5 digits by default) and padded to its width.
+impl fmt::Display for CoinFlipResult {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let precision = f.precision().unwrap_or(DEFAULT_PRECISION);
+        write_padded(f, &self.render_json(self.results.iter(), precision))
+    }
+}
+
+/// Iterates over the outcomes of a CoinFlipResult and their empirical results in sorted order.
+///
+/// # Examples
+/// ```
+/// use coin_flip_simulation;
+///
+/// for (outcome, result) in coin_flip_simulation::run(2, 100) {
+///     println!("{outcome}: {}", result.count);
+/// }
+/// ```
+impl IntoIterator for CoinFlipResult {
+    type Item = (String, EmpiricalResult);
+    type IntoIter = std::collections::btree_map::IntoIter<String, EmpiricalResult>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.results.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a CoinFlipResult {
+    type Item = (&'a String, &'a EmpiricalResult);
+    type IntoIter = std::collections::btree_map::Iter<'a, String, EmpiricalResult>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.results.iter()
+    }
+}
+
+/// Represents the estimated size of a simulation.
+///
+/// `num_outcomes` and `estimated_bytes` are `None` when they would overflow a `usize`, and
+/// `exceeds_limit` is set when the estimated size is more than [`MAX_RESULT_BYTES`] or overflows.
+#[derive(Debug, PartialEq)]
+pub struct SimEstimate {
+    pub flips_per_iteration: usize,
+    pub iterations: usize,
+    pub num_outcomes: Option<usize>,
+    pub estimated_bytes: Option<usize>,
+    pub overflows: bool,
+    pub exceeds_limit: bool,
+}
+
+/// Represents the throughput of a simulation measured by [`benchmark`].
+#[derive(Debug)]
+pub struct BenchReport {
+    pub elapsed: Duration,
+    pub flips_per_sec: f64,
+    pub iterations_per_sec: f64,
+    pub result_bytes: usize,
+}
+
+/// Represents the result of running a simulation with a varying number of flips per iteration.
+///
+/// Contains the empirical results of every outcome length, the total number of iterations, and
+/// the expected result of a single outcome for each number of flips per iteration.
+pub struct VariableLengthResult {
+    pub iterations: usize,
+    pub expected: BTreeMap<usize, EmpiricalResult>,
+    pub results: BTreeMap<String, EmpiricalResult>,
+}
+
+/// Represents the comparison of an outcome's observed and expected probabilities.
+#[derive(Debug, PartialEq)]
+pub struct OutcomeComparison {
+    pub outcome: String,
+    pub observed: f64,
+    pub expected: f64,
+    pub abs_error: f64,
+    pub rel_error: f64,
+}
+
+/// Accumulates the counts of several simulation runs into a single result.
+///
+/// # Examples
+/// ```
+/// use coin_flip_simulation::ResultAccumulator;
+///
+/// let mut accumulator = ResultAccumulator::new(3);
+/// accumulator.add_run(500);
+/// accumulator.add_run(1500);
+///
+/// let result = accumulator.finalize();
+///
+/// assert_eq!(result.iterations, 2000);
+/// assert_eq!(result.expected.count, 250);
+/// ```
+pub struct ResultAccumulator {
+    flips_per_iteration: usize,
+    iterations: usize,
+    counts: BTreeMap<String, usize>,
+}
+
+impl ResultAccumulator {
+    /// Creates an empty accumulator for runs with `flips_per_iteration` flips per iteration.
+    pub fn new(flips_per_iteration: usize) -> Self {
+        let counts = get_all_outcomes(flips_per_iteration)
+            .into_iter()
+            .map(|outcome| (outcome, 0))
+            .collect();
+
+        ResultAccumulator {
+            flips_per_iteration,
+            iterations: 0,
+            counts,
+        }
+    }
+
+    /// Simulates `iterations` more iterations and folds them into the accumulated counts.
+    pub fn add_run(&mut self, iterations: usize) {
+        for _ in 0..iterations {
+            *self
+                .counts
+                .entry(flip_outcome(self.flips_per_iteration))
+                .or_insert(0) += 1;
+        }
+
+        self.iterations += iterations;
+    }
+
+    /// Folds the counts of an already finished result into the accumulated counts.
+    ///
+    /// Returns a [`MergeError`] if the result was run with a different number of flips per
+    /// iteration than the accumulator.
+    pub fn add_result(&mut self, result: &CoinFlipResult) -> Result<(), MergeError> {
+        if result.flips_per_iteration != self.flips_per_iteration {
+            return Err(MergeError::FlipsMismatch {
+                expected: self.flips_per_iteration,
+                found: result.flips_per_iteration,
+            });
+        }
+
+        for (outcome, empirical) in result.results.iter() {
+            *self.counts.entry(outcome.clone()).or_insert(0) += empirical.count;
+        }
+
+        self.iterations += result.iterations;
+        Ok(())
+    }
+
+    /// Converts the accumulated counts into a CoinFlipResult.
+    pub fn finalize(self) -> CoinFlipResult {
+        CoinFlipResult::from_raw_counts(self.flips_per_iteration, self.iterations, self.counts)
+    }
+}
+
+/// Configures and runs a coin flip simulation.
+///
+/// # Examples
+/// ```
+/// use coin_flip_simulation::SimulationBuilder;
+///
+/// let result = SimulationBuilder::new(20, 100).include_unobserved(false).run();
+///
+/// assert!(result.results.len() <= 100);
+/// assert!(result.unobserved().is_empty());
+/// ```
+pub struct SimulationBuilder {
+    flips_per_iteration: usize,
+    iterations: usize,
+    include_unobserved: bool,
+}
+
+impl SimulationBuilder {
+    /// Creates a builder for `iterations` iterations of `flips_per_iteration` flips, which like
+    /// [`run`] includes every possible outcome in the results by default.
+    pub fn new(flips_per_iteration: usize, iterations: usize) -> Self {
+        SimulationBuilder {
+            flips_per_iteration,
+            iterations,
+            include_unobserved: true,
+        }
+    }
+
+    /// Sets whether outcomes that never occur are included in the results with a count of 0.
+    ///
+    /// Without them, `results` only holds the outcomes that occurred, so it can be much smaller
+    /// than the `2^flips_per_iteration` possible outcomes and [`CoinFlipResult::unobserved`] is
+    /// always empty. Methods that go through `results`, such as [`CoinFlipResult::chi_square`],
+    /// then only account for the observed outcomes.
+    pub fn include_unobserved(mut self, include_unobserved: bool) -> Self {
+        self.include_unobserved = include_unobserved;
+        self
+    }
+
+    /// Runs the configured simulation.
+    pub fn run(self) -> CoinFlipResult {
+        let mut results: BTreeMap<String, usize> = if self.include_unobserved {
+            get_all_outcomes(self.flips_per_iteration)
+                .into_iter()
+                .map(|outcome| (outcome, 0))
+                .collect()
+        } else {
+            BTreeMap::new()
+        };
+
+        for _ in 0..self.iterations {
+            *results
+                .entry(flip_outcome(self.flips_per_iteration))
+                .or_insert(0) += 1;
+        }
+
+        CoinFlipResult::from_raw_counts(self.flips_per_iteration, self.iterations, results)
+    }
+}
+
+/// Error returned when combining results that don't share the same outcome space.
+#[derive(Debug, PartialEq)]
+pub enum MergeError {
+    /// The results were run with a different number of flips per iteration.
+    FlipsMismatch { expected: usize, found: usize },
+    /// An outcome is in one result but not the other.
+    OutcomeMismatch(String),
+}
+
+impl fmt::Display for MergeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MergeError::FlipsMismatch { expected, found } => write!(
+                f,
+                "expected {expected} flips per iteration but found {found}"
+            ),
+            MergeError::OutcomeMismatch(outcome) => {
+                write!(f, "outcome {outcome:?} is only in one of the results")
+            }
+        }
+    }
+}
+
+impl Error for MergeError {}
+
+/// Error returned when decoding a CoinFlipResult from bytes fails.
+#[derive(Debug, PartialEq)]
+pub enum DecodeError {
+    /// The bytes ended before the result was fully decoded.
+    UnexpectedEnd,
+    /// An outcome isn't valid UTF-8.
+    InvalidUtf8,
+    /// A symbol isn't a valid `char`.
+    InvalidSymbol,
+    /// There are fewer than two symbols, or a symbol is repeated.
+    InvalidSymbols,
+    /// The number of possible outcomes is 0.
+    NoOutcomes,
+    /// A value doesn't fit in a `usize` on the current target.
+    OutOfRange,
+    /// There are bytes left over after the result was decoded.
+    TrailingBytes,
+    /// A flag byte is neither 0 nor 1.
+    InvalidFlag,
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DecodeError::UnexpectedEnd => write!(f, "unexpected end of bytes"),
+            DecodeError::InvalidUtf8 => write!(f, "outcome is not valid UTF-8"),
+            DecodeError::InvalidSymbol => write!(f, "symbol is not a valid char"),
+            DecodeError::InvalidSymbols => write!(f, "symbols must be at least two distinct chars"),
+            DecodeError::NoOutcomes => write!(f, "number of possible outcomes is zero"),
+            DecodeError::OutOfRange => write!(f, "value is out of range for usize"),
+            DecodeError::TrailingBytes => write!(f, "unexpected bytes after the end of the result"),
+            DecodeError::InvalidFlag => write!(f, "flag byte is neither 0 nor 1"),
+        }
+    }
+}
+
+impl Error for DecodeError {}
+
+struct ByteReader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> ByteReader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], DecodeError> {
+        if self.bytes.len() < len {
+            return Err(DecodeError::UnexpectedEnd);
+        }
+
+        let (taken, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    fn read_u8(&mut self) -> Result<u8, DecodeError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn read_u32(&mut self) -> Result<u32, DecodeError> {
+        let bytes = self.take(4)?;
+        Ok(u32::from_le_bytes(bytes.try_into().unwrap()))
+    }
+
+    fn read_u64(&mut self) -> Result<u64, DecodeError> {
+        let bytes = self.take(8)?;
+        Ok(u64::from_le_bytes(bytes.try_into().unwrap()))
+    }
+
+    fn read_usize(&mut self) -> Result<usize, DecodeError> {
+        self.read_u64()?
+            .try_into()
+            .map_err(|_| DecodeError::OutOfRange)
+    }
+
+    fn read_string(&mut self) -> Result<String, DecodeError> {
+        let len = self.read_u32()? as usize;
+        String::from_utf8(self.take(len)?.to_vec()).map_err(|_| DecodeError::InvalidUtf8)
+    }
+}
+
+/// Summarizes the number of flips it took for a pattern to first appear, as returned by
+/// [`run_waiting_time_summary`].
+#[derive(Debug)]
+pub struct WaitingTimeSummary {
+    pub trials: usize,
+    pub mean: f64,
+    /// The approximate median number of flips.
+    pub median: f64,
+    /// The approximate 95th percentile of the number of flips.
+    pub p95: f64,
+}
+
+/// The kind of random number generator a simulation flips coins with.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RngKind {
+    /// A small, fast generator (`SmallRng`) whose output is statistically good enough for
+    /// simulation but predictable from a few outputs. This is the quicker choice for large runs.
+    Fast,
+    /// The ChaCha20 stream cipher (`ChaCha20Rng`), which is cryptographically secure but several
+    /// times slower per flip than [`RngKind::Fast`].
+    Crypto,
+}
+
+/// Decides who wins a game of [`run_majority`] when heads and tails come up equally often.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TieRule {
+    HeadsWins,
+    TailsWins,
+}
+
+/// Represents how far a result's empirical probabilities are from a reference distribution, as
+/// returned by [`CoinFlipResult::compare_to_reference`].
+#[derive(Debug, PartialEq)]
+pub struct ComparisonStats {
+    pub chi_square: f64,
+    pub kl_divergence: f64,
+    pub total_variation: f64,
+}
+
+/// Represents how often two patterns occur in the same iteration, as returned by
+/// [`joint_pattern_probability`].
+#[derive(Debug, PartialEq)]
+pub struct JointPatternProbability {
+    /// The probability that both patterns occur.
+    pub joint: f64,
+    /// The probability that the first pattern occurs.
+    pub marginal_a: f64,
+    /// The probability that the second pattern occurs.
+    pub marginal_b: f64,
+}
+
+/// Represents the scalar statistics of a result, as returned by [`CoinFlipResult::summary`].
+#[derive(Debug, PartialEq)]
+pub struct SimSummary {
+    /// Pearson's chi-square statistic against the expected counts.
+    pub chi_square: f64,
+    /// The Shannon entropy of the empirical distribution, in bits.
+    pub entropy: f64,
+    /// The KL divergence of the empirical distribution from the expected one, in nats.
+    pub kl_divergence: f64,
+    /// The mean squared error of the empirical probabilities.
+    pub mse: f64,
+    /// The mean number of heads per iteration.
+    pub mean_heads: f64,
+    /// The variance of the number of heads per iteration.
+    pub heads_variance: f64,
+}
+
+/// Represents the result of [`run_full`], with the same iterations keyed both by outcome and by
+/// number of heads.
+pub struct FullResult {
+    pub sequences: CoinFlipResult,
+    pub head_counts: BTreeMap<usize, EmpiricalResult>,
+}
+
+/// Represents an empirical result.
+///
+/// Contains the raw count of an outcome and the observed probability.
+#[derive(Clone, Copy)]
+pub struct EmpiricalResult {
+    pub count: usize,
+    pub probability: f64,
+}
+
+impl EmpiricalResult {
+    fn new(count: usize, iterations: usize) -> Self {
+        let probability: f64 = (count as f64) / (iterations as f64);
+
+        EmpiricalResult { count, probability }
+    }
+
+    /// Gets the expected result for a specified number of iterations and flips_per_iteration.
+    pub fn expected(flips_per_iteration: usize, iterations: usize) -> Self {
+        let num_outcomes = get_num_outcomes(flips_per_iteration);
+        let count = iterations / num_outcomes;
+
+        EmpiricalResult::new(count, iterations)
+    }
+
+    /// Gets the Wilson score interval for the probability of this result over `iterations`
+    /// iterations, where `z` is the standard normal quantile of the confidence level, such as 1.96
+    /// for 95%.
+    ///
+    /// Unlike the Wald interval `p ± z * sqrt(p * (1 - p) / n)`, it always lies within `[0, 1]` and
+    /// doesn't collapse to a single point for outcomes that never or always occurred. With no
+    /// iterations the interval is `(0, 1)`.
+    ///
+    /// # Examples
+    /// ```
+    /// use coin_flip_simulation;
+    ///
+    /// let result = coin_flip_simulation::run(1, 1000);
+    /// let heads = &result.results["H"];
+    /// let (low, high) = heads.wilson_interval(1000, 1.96);
+    ///
+    /// assert!(low <= heads.probability && heads.probability <= high);
+    /// ```
+    pub fn wilson_interval(&self, iterations: usize, z: f64) -> (f64, f64) {
+        if iterations == 0 {
+            return (0.0, 1.0);
+        }
+
+        let n = iterations as f64;
+        let p = self.count as f64 / n;
+        let z2 = z * z;
+        let denominator = 1.0 + z2 / n;
+        let center = (p + z2 / (2.0 * n)) / denominator;
+        let half_width = z / denominator * (p * (1.0 - p) / n + z2 / (4.0 * n * n)).sqrt();
+
+        (
+            (center - half_width).max(0.0),
+            (center + half_width).min(1.0),
+        )
+    }
+
+    /// Combines the counts of two results and recomputes the probability over `total_iterations`,
+    /// the combined number of iterations of both.
+    ///
+    /// # Examples
+    /// ```
+    /// use coin_flip_simulation;
+    ///
+    /// let first = coin_flip_simulation::run(1, 100);
+    /// let second = coin_flip_simulation::run(1, 300);
+    /// let combined = first.results["H"].combine(&second.results["H"], 400);
+    ///
+    /// assert_eq!(combined.count, first.results["H"].count + second.results["H"].count);
+    /// ```
+    pub fn combine(&self, other: &EmpiricalResult, total_iterations: usize) -> EmpiricalResult {
+        EmpiricalResult::new(self.count + other.count, total_iterations)
+    }
+
+    /// Gets the odds of this outcome over `iterations` iterations as `(for, against)` in lowest
+    /// terms, which is its count against the number of iterations it didn't occur in, such as
+    /// `(1, 7)` for a probability of 0.125.
+    ///
+    /// An outcome that never occurred has odds of `(0, 1)` and one that always occurred has odds
+    /// of `(1, 0)`.
+    ///
+    /// # Examples
+    /// ```
+    /// use coin_flip_simulation;
+    ///
+    /// let result = coin_flip_simulation::run(0, 10);
+    ///
+    /// assert_eq!(result.results[""].odds(result.iterations), (1, 0));
+    /// ```
+    pub fn odds(&self, iterations: usize) -> (u64, u64) {
+        if self.count == 0 {
+            return (0, 1);
+        }
+
+        let (count, others) = (
+            self.count as u64,
+            iterations.saturating_sub(self.count) as u64,
+        );
+
+        let mut gcd = (count, others);
+        while gcd.1 != 0 {
+            gcd = (gcd.1, gcd.0 % gcd.1);
+        }
+
+        (count / gcd.0, others / gcd.0)
+    }
+
+    /// Gets the odds of this outcome over `iterations` iterations formatted as `for:against`,
+    /// such as `1:7`.
+    pub fn odds_string(&self, iterations: usize) -> String {
+        let (odds_for, odds_against) = self.odds(iterations);
+        format!("{odds_for}:{odds_against}")
+    }
+}
+
+/// Formats an EmpiricalResult with its probability rounded to the formatter's precision (5 digits
+/// by default) and padded to its width.
+impl fmt::Display for EmpiricalResult {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let precision = f.precision().unwrap_or(DEFAULT_PRECISION);
+        let s = format!(
+            "{{count: {}, probability: {:.precision$}}}",
+            self.count, self.probability
+        );
+        write_padded(f, &s)
+    }
+}
+
+/// The number of iterations per outcome below which empirical probabilities are unreliable.
+const MIN_ITERATIONS_PER_OUTCOME: usize = 5;
+
+/// How far the empirical probabilities may sum from 1 before a result is considered invalid.
+const PROBABILITY_SUM_TOLERANCE: f64 = 1e-9;
+
+/// The number of digits probabilities are displayed with when no precision is given.
+const DEFAULT_PRECISION: usize = 5;
+
+/// Writes `s` padded to the formatter's width with its fill and alignment, aligning left by
+/// default.
+///
+/// Unlike `Formatter::pad`, this doesn't truncate `s` to the formatter's precision, which is used
+/// for the probabilities instead.
+fn write_padded(f: &mut fmt::Formatter, s: &str) -> fmt::Result {
+    let len = s.chars().count();
+    let padding = f.width().unwrap_or(0).saturating_sub(len);
+    let (before, after) = match f.align() {
+        Some(fmt::Alignment::Right) => (padding, 0),
+        Some(fmt::Alignment::Center) => (padding / 2, padding - padding / 2),
+        _ => (0, padding),
+    };
+
+    let fill = f.fill();
+    for _ in 0..before {
+        write!(f, "{fill}")?;
+    }
+    f.write_str(s)?;
+    for _ in 0..after {
+        write!(f, "{fill}")?;
+    }
+
+    Ok(())
+}
+
+/// Error returned when the parameters of a simulation are invalid.
+#[derive(Debug, PartialEq)]
+pub enum SimError {
+    /// A parameter can't be converted to a `usize`.
+    OutOfRange,
+    /// The number of possible outcomes doesn't fit in a `usize`.
+    Overflow { flips_per_iteration: usize },
+    /// The simulation has no iterations to run.
+    ZeroIterations,
+    /// A probability of heads isn't between 0 and 1.
+    InvalidBias(f64),
+    /// An outcome or pattern isn't made of valid symbols.
+    InvalidOutcome(String),
+    /// A pattern is longer than the flips it's matched against.
+    PatternTooLong { pattern_len: usize, max_len: usize },
+    /// A simulation that runs iterations in pairs was given an odd number of iterations.
+    OddIterations(usize),
+    /// A flip position is repeated or isn't less than the number of flips per iteration.
+    InvalidPosition {
+        position: usize,
+        flips_per_iteration: usize,
+    },
+    /// A starting bankroll isn't strictly between 0 and the target.
+    InvalidBankroll { start: u64, target: u64 },
+    /// The result's estimated size is more than [`MAX_RESULT_BYTES`].
+    TooLarge { flips_per_iteration: usize },
+    /// An outcome has no expected probability.
+    MissingExpected(String),
+    /// An outcome's expected probability isn't finite and positive.
+    InvalidProbability { outcome: String, probability: f64 },
+    /// Expected probabilities don't sum to 1.
+    ProbabilitySum(f64),
+}
+
+impl fmt::Display for SimError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SimError::OutOfRange => write!(f, "parameter is out of range for usize"),
+            SimError::Overflow {
+                flips_per_iteration,
+            } => write!(
+                f,
+                "number of outcomes for {flips_per_iteration} flips per iteration overflows usize"
+            ),
+            SimError::ZeroIterations => write!(f, "iterations must be greater than zero"),
+            SimError::InvalidBias(p_heads) => write!(f, "bias {p_heads} is not between 0 and 1"),
+            SimError::InvalidOutcome(outcome) => write!(f, "{outcome:?} is not a valid outcome"),
+            SimError::PatternTooLong {
+                pattern_len,
+                max_len,
+            } => write!(
+                f,
+                "pattern of length {pattern_len} is longer than {max_len} flips"
+            ),
+            SimError::OddIterations(iterations) => {
+                write!(f, "iterations must be even but {iterations} is odd")
+            }
+            SimError::InvalidPosition {
+                position,
+                flips_per_iteration,
+            } => write!(
+                f,
+                "position {position} is repeated or out of range for {flips_per_iteration} flips \
+                 per iteration"
+            ),
+            SimError::InvalidBankroll { start, target } => write!(
+                f,
+                "starting bankroll {start} is not strictly between 0 and the target {target}"
+            ),
+            SimError::TooLarge {
+                flips_per_iteration,
+            } => write!(
+                f,
+                "results for {flips_per_iteration} flips per iteration would take more than \
+                 {MAX_RESULT_BYTES} bytes"
+            ),
+            SimError::MissingExpected(outcome) => {
+                write!(f, "outcome {outcome:?} has no expected probability")
+            }
+            SimError::InvalidProbability {
+                outcome,
+                probability,
+            } => write!(
+                f,
+                "expected probability {probability} of {outcome:?} is not finite and positive"
+            ),
+            SimError::ProbabilitySum(total) => {
+                write!(f, "expected probabilities sum to {total} rather than 1")
+            }
+        }
+    }
+}
+
+impl Error for SimError {}
+
+/// Represents a single side of a coin.
+#[derive(Debug, PartialEq)]
+pub enum Coin {
+    Heads,
+    Tails,
+}
+
+impl Coin {
+    /// Flips a fair coin.
+    pub fn flip() -> Self {
+        Coin::from_bool(rand::random())
+    }
+
+    /// Flips a fair coin using the given random number generator.
+    pub fn flip_with<R: Rng>(rng: &mut R) -> Self {
+        Coin::from_bool(rng.gen())
+    }
+
+    /// Converts a Coin to a bool, where heads is `true`.
+    ///
+    /// # Examples
+    /// ```
+    /// use coin_flip_simulation::Coin;
+    ///
+    /// assert!(Coin::Heads.to_bool());
+    /// assert!(!Coin::Tails.to_bool());
+    /// ```
+    pub fn to_bool(&self) -> bool {
+        *self == Coin::Heads
+    }
+
+    /// Converts a bool to a Coin, where `true` is heads.
+    pub fn from_bool(heads: bool) -> Self {
+        if heads {
+            Coin::Heads
+        } else {
+            Coin::Tails
+        }
+    }
+}
+
+impl fmt::Display for Coin {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use Coin::{Heads, Tails};
+
+        match self {
+            Heads => write!(f, "H"),
+            Tails => write!(f, "T"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_all_outcomes_4() {
+        use Coin::{Heads, Tails};
+
+        let outcomes = get_all_outcomes(4);
+
+        let mut expected = Vec::new();
+        for i in [Heads, Tails] {
+            for j in [Heads, Tails] {
+                for k in [Heads, Tails] {
+                    for l in [Heads, Tails] {
+                        expected.push(format!("{i}{j}{k}{l}"));
+                    }
+                }
+            }
+        }
+
+        assert_eq!(outcomes, expected);
+    }
+
+    #[test]
+    fn test_get_all_outcomes_6() {
+        use Coin::{Heads, Tails};
+
+        let outcomes = get_all_outcomes(6);
+
+        let mut expected = Vec::new();
+        for i in [Heads, Tails] {
+            for j in [Heads, Tails] {
+                for k in [Heads, Tails] {
+                    for l in [Heads, Tails] {
+                        for m in [Heads, Tails] {
+                            for n in [Heads, Tails] {
+                                expected.push(format!("{i}{j}{k}{l}{m}{n}"));
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        assert_eq!(outcomes, expected);
+    }
+
+    #[test]
+    fn test_run_without_replacement_keeps_heads() {
+        let result = run_without_replacement(3, 2, 500).unwrap();
+
+        assert_eq!(result.results.len(), 10);
+        for (outcome, empirical) in result.results.iter() {
+            assert_eq!(num_heads(outcome), 3);
+            assert_eq!(outcome.len(), 5);
+            assert!(empirical.count <= 500);
+        }
+
+        let total: usize = result.results.values().map(|r| r.count).sum();
+        assert_eq!(total, 500);
+    }
+
+    #[test]
+    fn test_is_valid_outcome_valid_key() {
+        let result = run(4, 10);
+
+        assert!(result.is_valid_outcome("HTTH"));
+        assert!(result.is_valid_outcome("TTTT"));
+    }
+
+    #[test]
+    fn test_is_valid_outcome_wrong_length() {
+        let result = run(4, 10);
+
+        assert!(!result.is_valid_outcome("HTT"));
+        assert!(!result.is_valid_outcome("HTTHH"));
+        assert!(!result.is_valid_outcome(""));
+    }
+
+    #[test]
+    fn test_is_valid_outcome_bad_character() {
+        let result = run(4, 10);
+
+        assert!(!result.is_valid_outcome("HTTX"));
+        assert!(!result.is_valid_outcome("htth"));
+    }
+
+    #[test]
+    fn test_probability_at_least_k_heads_complement() {
+        let result = run(5, 1000);
+
+        for k in 1..=5 {
+            let total =
+                result.probability_at_least_k_heads(k) + result.probability_at_most_k_heads(k - 1);
+            assert!((total - 1.0).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_probability_at_least_k_heads_bounds() {
+        let result = run(3, 1000);
+
+        assert!((result.probability_at_least_k_heads(0) - 1.0).abs() < 1e-9);
+        assert!((result.probability_at_most_k_heads(3) - 1.0).abs() < 1e-9);
+        assert_eq!(result.probability_at_least_k_heads(4), 0.0);
+    }
+
+    #[test]
+    fn test_run_from_u64() {
+        let result = run_from(3u64, 800u64).unwrap();
+
+        assert_eq!(result.iterations, 800);
+        assert_eq!(result.flips_per_iteration, 3);
+    }
+
+    #[test]
+    fn test_run_from_exceeding_narrow_usize() {
+        // Converts like a u64 would on a target with a 32-bit usize.
+        struct NarrowU64(u64);
+
+        impl TryFrom<NarrowU64> for usize {
+            type Error = std::num::TryFromIntError;
+
+            fn try_from(value: NarrowU64) -> Result<Self, Self::Error> {
+                u32::try_from(value.0).map(|n| n as usize)
+            }
+        }
+
+        assert!(run_from(NarrowU64(3), 10u64).is_ok());
+        assert_eq!(
+            run_from(NarrowU64(u64::from(u32::MAX) + 1), 10u64).err(),
+            Some(SimError::OutOfRange)
+        );
+        assert_eq!(
+            run_from(3u64, NarrowU64(u64::MAX)).err(),
+            Some(SimError::OutOfRange)
+        );
+    }
+
+    #[test]
+    fn test_run_from_overflow() {
+        let flips_per_iteration = usize::BITS as usize;
+
+        assert_eq!(
+            run_from(flips_per_iteration, 10).err(),
+            Some(SimError::Overflow {
+                flips_per_iteration
+            })
+        );
+    }
+
+    #[test]
+    fn test_run_from_zero_iterations() {
+        assert_eq!(run_from(3, 0).err(), Some(SimError::ZeroIterations));
+    }
+
+    #[test]
+    fn test_sim_error_display() {
+        assert_eq!(
+            SimError::ZeroIterations.to_string(),
+            "iterations must be greater than zero"
+        );
+        assert_eq!(
+            SimError::Overflow {
+                flips_per_iteration: 64
+            }
+            .to_string(),
+            "number of outcomes for 64 flips per iteration overflows usize"
+        );
+    }
+
+    #[test]
+    fn test_to_markdown_table() {
+        let result = run(2, 100);
+        let table = result.to_markdown_table();
+        let lines: Vec<&str> = table.lines().collect();
+
+        assert_eq!(lines.len(), 6);
+        assert_eq!(lines[0], "| Outcome | Count | Probability |");
+        assert_eq!(lines[1], "| :--- | ---: | ---: |");
+
+        let hh = &result.results["HH"];
+        assert_eq!(
+            lines[2],
+            format!("| HH | {} | {:.5} |", hh.count, hh.probability)
+        );
+        assert!(lines[5].starts_with("| TT | "));
+    }
+
+    #[test]
+    fn test_result_accumulator_two_runs() {
+        let mut accumulator = ResultAccumulator::new(2);
+        accumulator.add_run(300);
+        accumulator.add_run(100);
+
+        let result = accumulator.finalize();
+        let total: usize = result.results.values().map(|r| r.count).sum();
+
+        assert_eq!(result.iterations, 400);
+        assert_eq!(result.flips_per_iteration, 2);
+        assert_eq!(result.results.len(), 4);
+        assert_eq!(total, 400);
+        assert_eq!(result.expected.count, 100);
+    }
+
+    #[test]
+    fn test_result_accumulator_add_result() {
+        let first = run(3, 200);
+        let mut accumulator = ResultAccumulator::new(3);
+        accumulator.add_run(100);
+        accumulator.add_result(&first).unwrap();
+
+        let result = accumulator.finalize();
+        let total: usize = result.results.values().map(|r| r.count).sum();
+
+        assert_eq!(result.iterations, 300);
+        assert_eq!(total, 300);
+    }
+
+    #[test]
+    fn test_result_accumulator_flips_mismatch() {
+        let mut accumulator = ResultAccumulator::new(3);
+
+        assert_eq!(
+            accumulator.add_result(&run(4, 10)),
+            Err(MergeError::FlipsMismatch {
+                expected: 3,
+                found: 4
+            })
+        );
+    }
+
+    fn result_from_counts(flips_per_iteration: usize, counts: &[(&str, usize)]) -> CoinFlipResult {
+        let iterations = counts.iter().map(|(_, count)| count).sum();
+        let counts = counts
+            .iter()
+            .map(|(outcome, count)| (outcome.to_string(), *count))
+            .collect();
+
+        CoinFlipResult::from_raw_counts(flips_per_iteration, iterations, counts)
+    }
+
+    #[test]
+    fn test_comparison() {
+        let result = result_from_counts(2, &[("HH", 40), ("HT", 30), ("TH", 20), ("TT", 10)]);
+        let comparison = result.comparison();
+
+        assert_eq!(comparison.len(), get_num_outcomes(2));
+
+        let expected_errors = [("HH", 0.15), ("HT", 0.05), ("TH", 0.05), ("TT", 0.15)];
+        for (c, (outcome, abs_error)) in comparison.iter().zip(expected_errors) {
+            assert_eq!(c.outcome, outcome);
+            assert_eq!(c.expected, 0.25);
+            assert!((c.abs_error - abs_error).abs() < 1e-9);
+            assert!((c.rel_error - abs_error / 0.25).abs() < 1e-9);
+        }
+        assert_eq!(comparison[0].observed, 0.4);
+    }
+
+    #[test]
+    fn test_run_with_burn_in_counts_remaining() {
+        let result = run_with_burn_in(2, 500, 100);
+        let total: usize = result.results.values().map(|r| r.count).sum();
+
+        assert_eq!(result.iterations, 400);
+        assert_eq!(total, 400);
+    }
+
+    #[test]
+    fn test_run_with_burn_in_everything_discarded() {
+        let result = run_with_burn_in(3, 50, 50);
+
+        assert_eq!(result.iterations, 0);
+        assert_eq!(result.results.len(), 8);
+        assert!(result.results.values().all(|r| r.count == 0));
+        assert_eq!(run_with_burn_in(3, 50, 80).iterations, 0);
+    }
+
+    #[test]
+    fn test_rescale_counts_total() {
+        let result = run(4, 5000);
+        let rescaled = result.rescale_counts(10000);
+        let total: f64 = rescaled.values().sum();
+
+        assert_eq!(rescaled.len(), 16);
+        assert!((total - 10000.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_rescale_counts_values() {
+        let result = result_from_counts(1, &[("H", 30), ("T", 70)]);
+        let rescaled = result.rescale_counts(1000);
+
+        assert!((rescaled["H"] - 300.0).abs() < 1e-9);
+        assert!((rescaled["T"] - 700.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_lag1_autocorrelation_alternating() {
+        let result = result_from_counts(4, &[("HTHT", 50), ("THTH", 50)]);
+
+        assert!((result.lag1_autocorrelation() + 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_lag1_autocorrelation_repeating() {
+        let result = result_from_counts(4, &[("HHHH", 50), ("TTTT", 50)]);
+
+        assert!((result.lag1_autocorrelation() - 1.0).abs() < 1e-9);
+        assert!(run(1, 100).lag1_autocorrelation().is_nan());
+    }
+
+    #[test]
+    fn test_top_n_sorted() {
+        let result = result_from_counts(2, &[("HH", 10), ("HT", 40), ("TH", 10), ("TT", 40)]);
+        let top: Vec<(&str, usize)> = result
+            .top_n(3)
+            .into_iter()
+            .map(|(k, v)| (k.as_str(), v.count))
+            .collect();
+
+        assert_eq!(top, vec![("HT", 40), ("TT", 40), ("HH", 10)]);
+    }
+
+    #[test]
+    fn test_top_n_larger_than_map() {
+        let result = run(3, 100);
+
+        assert_eq!(result.top_n(100).len(), 8);
+    }
+
+    #[test]
+    fn test_to_json_string_top_n() {
+        let result = result_from_counts(2, &[("HH", 10), ("HT", 40), ("TH", 20), ("TT", 30)]);
+        let json = result.to_json_string_top_n(2);
+
+        assert!(json.contains("HT: {count: 40"));
+        assert!(json.contains("TT: {count: 30"));
+        assert!(!json.contains("HH"));
+        assert!(json.find("HT").unwrap() < json.find("TT").unwrap());
+        assert_eq!(
+            result.to_json_string_top_n(4).lines().count(),
+            result.to_json_string().lines().count()
+        );
+    }
+
+    #[test]
+    fn test_imbalance_uniform_vs_concentrated() {
+        let uniform = result_from_counts(2, &[("HH", 25), ("HT", 25), ("TH", 25), ("TT", 25)]);
+        let concentrated = result_from_counts(2, &[("HH", 100), ("HT", 0), ("TH", 0), ("TT", 0)]);
+        let skewed = result_from_counts(2, &[("HH", 40), ("HT", 30), ("TH", 20), ("TT", 10)]);
+
+        assert!(uniform.imbalance().abs() < 1e-9);
+        assert!((concentrated.imbalance() - 1.0).abs() < 1e-9);
+        // sum over ordered pairs of |p_i - p_j| is 2.0, divided by 2 * 3.
+        assert!((skewed.imbalance() - 1.0 / 3.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_run_variable_two_flip_counts() {
+        let result = run_variable(&[2, 3], 400);
+
+        assert_eq!(result.iterations, 800);
+        assert_eq!(result.results.len(), 4 + 8);
+        assert_eq!(result.expected[&2].count, 100);
+        assert_eq!(result.expected[&3].count, 50);
+        assert_eq!(result.expected[&2].probability, 0.125);
+
+        let count_of_length = |len: usize| -> usize {
+            result
+                .results
+                .iter()
+                .filter(|(k, _)| k.len() == len)
+                .map(|(_, v)| v.count)
+                .sum()
+        };
+        assert_eq!(count_of_length(2), 400);
+        assert_eq!(count_of_length(3), 400);
+
+        let total: f64 = result.results.values().map(|r| r.probability).sum();
+        assert!((total - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_run_variable_repeated_flip_count() {
+        let result = run_variable(&[2, 2], 400);
+
+        assert_eq!(result.results.len(), 4);
+        assert_eq!(result.results.values().map(|r| r.count).sum::<usize>(), 800);
+        assert_eq!(result.expected[&2].count, 200);
+    }
+
+    #[test]
+    fn test_to_bytes_round_trip() {
+        let result = run(4, 1000);
+        let bytes = result.to_bytes();
+        let decoded = CoinFlipResult::from_bytes(&bytes).unwrap();
+
+        assert_eq!(decoded.to_bytes(), bytes);
+        assert_eq!(decoded.flips_per_iteration, result.flips_per_iteration);
+        assert_eq!(decoded.symbols, result.symbols);
+        assert_eq!(decoded.iterations, result.iterations);
+        assert_eq!(decoded.expected.count, result.expected.count);
+        assert_eq!(decoded.expected.probability, result.expected.probability);
+        assert_eq!(
+            decoded.expected_probability(),
+            result.expected_probability()
+        );
+        for ((k1, v1), (k2, v2)) in decoded.results.iter().zip(result.results.iter()) {
+            assert_eq!(k1, k2);
+            assert_eq!(v1.count, v2.count);
+            assert_eq!(v1.probability, v2.probability);
+        }
+    }
+
+    #[test]
+    fn test_to_bytes_layout() {
+        let result = result_from_counts(1, &[("H", 3), ("T", 1)]);
+        let bytes = result.to_bytes();
+
+        assert_eq!(bytes.len(), 8 * 4 + 4 + 4 * 2 + 8 + 2 * (4 + 1 + 8) + 1);
+        assert_eq!(bytes.last(), Some(&0));
+        assert_eq!(&bytes[..8], &1u64.to_le_bytes());
+        assert_eq!(&bytes[8..16], &4u64.to_le_bytes());
+    }
+
+    #[test]
+    fn test_from_bytes_errors() {
+        const U32: usize = std::mem::size_of::<u32>();
+        const U64: usize = std::mem::size_of::<u64>();
+        // The header is the flips, iterations, expected count and number of outcomes.
+        const HEADER: usize = 4 * U64;
+        const NUM_OUTCOMES: usize = 3 * U64;
+        let bytes = run(2, 10).to_bytes();
+
+        assert_eq!(
+            CoinFlipResult::from_bytes(&bytes[..bytes.len() - 1]).err(),
+            Some(DecodeError::UnexpectedEnd)
+        );
+
+        let mut trailing = bytes.clone();
+        trailing.push(0);
+        assert_eq!(
+            CoinFlipResult::from_bytes(&trailing).err(),
+            Some(DecodeError::TrailingBytes)
+        );
+
+        let mut no_outcomes = bytes.clone();
+        no_outcomes[NUM_OUTCOMES..HEADER].copy_from_slice(&0u64.to_le_bytes());
+        assert_eq!(
+            CoinFlipResult::from_bytes(&no_outcomes).err(),
+            Some(DecodeError::NoOutcomes)
+        );
+
+        // The symbols follow the header as a u32 count and a u32 per symbol.
+        let with_symbols = |symbols: &[char]| {
+            let mut crafted = bytes[..HEADER].to_vec();
+            crafted.extend_from_slice(&(symbols.len() as u32).to_le_bytes());
+            for &symbol in symbols {
+                crafted.extend_from_slice(&u32::from(symbol).to_le_bytes());
+            }
+            crafted.extend_from_slice(&bytes[HEADER + U32 + 2 * U32..]);
+            crafted
+        };
+        assert!(CoinFlipResult::from_bytes(&with_symbols(&['H', 'T'])).is_ok());
+        for symbols in [&[][..], &['H'], &['H', 'H'], &['H', 'T', 'H']] {
+            assert_eq!(
+                CoinFlipResult::from_bytes(&with_symbols(symbols)).err(),
+                Some(DecodeError::InvalidSymbols)
+            );
+        }
+
+        let mut bad_flag = bytes.clone();
+        *bad_flag.last_mut().unwrap() = 2;
+        assert_eq!(
+            CoinFlipResult::from_bytes(&bad_flag).err(),
+            Some(DecodeError::InvalidFlag)
+        );
+    }
+
+    fn assert_same_comparison(decoded: &CoinFlipResult, result: &CoinFlipResult) {
+        for (a, b) in decoded.comparison().iter().zip(result.comparison()) {
+            assert_eq!(a.outcome, b.outcome);
+            assert_eq!(a.expected, b.expected);
+            assert_eq!(a.abs_error, b.abs_error);
+        }
+        assert_eq!(decoded.chi_square(), result.chi_square());
+    }
+
+    #[test]
+    fn test_to_bytes_round_trip_expected_map() {
+        let mut result = result_from_counts(1, &[("H", 85), ("T", 15)]);
+        let expected = BTreeMap::from([("H".to_string(), 0.9), ("T".to_string(), 0.1)]);
+        result.set_expected_map(expected).unwrap();
+        let bytes = result.to_bytes();
+        let decoded = CoinFlipResult::from_bytes(&bytes).unwrap();
+
+        assert_eq!(decoded.comparison()[0].expected, 0.9);
+        assert_same_comparison(&decoded, &result);
+        assert_eq!(decoded.to_bytes(), bytes);
+    }
+
+    #[test]
+    fn test_to_bytes_round_trip_edge() {
+        let result = run_with_edge(2, 1000, 0.2).unwrap();
+        let decoded = CoinFlipResult::from_bytes(&result.to_bytes()).unwrap();
+
+        assert_eq!(decoded.symbols, ['H', 'T', 'E']);
+        assert_same_comparison(&decoded, &result);
+    }
+
+    #[test]
+    fn test_coin_bool_round_trip() {
+        assert_eq!(Coin::from_bool(true), Coin::Heads);
+        assert_eq!(Coin::from_bool(false), Coin::Tails);
+
+        for heads in [true, false] {
+            assert_eq!(Coin::from_bool(heads).to_bool(), heads);
+        }
+        for coin in [Coin::Heads, Coin::Tails] {
+            assert_eq!(Coin::from_bool(coin.to_bool()), coin);
+        }
+    }
+
+    #[test]
+    fn test_expected_float_count_non_divisible() {
+        let result = result_from_counts(3, &[("HHH", 3), ("HTH", 2), ("TTT", 5)]);
+
+        assert_eq!(result.iterations, 10);
+        assert_eq!(result.expected.count, 1);
+        assert_eq!(result.expected.probability, 0.1);
+        assert_eq!(result.expected_probability(), 0.125);
+        assert_eq!(result.expected_float_count(), 1.25);
+    }
+
+    #[test]
+    fn test_comparison_uses_float_expected() {
+        let result = result_from_counts(3, &[("HHH", 3), ("HTH", 2), ("TTT", 5)]);
+        let comparison = result.comparison();
+
+        assert!(comparison.iter().all(|c| c.expected == 0.125));
+        assert!((comparison[0].abs_error - 0.175).abs() < 1e-9);
+        assert!((comparison[1].abs_error - 0.075).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_expected_float_count_without_replacement() {
+        let result = run_without_replacement(2, 2, 10).unwrap();
+
+        assert_eq!(result.expected_probability(), 1.0 / 6.0);
+        assert!((result.expected_float_count() - 10.0 / 6.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_run_with_symbols() {
+        let result = run_with_symbols(3, 400, ['1', '0']);
+        let total: usize = result.results.values().map(|r| r.count).sum();
+
+        assert_eq!(result.results.len(), 8);
+        assert_eq!(total, 400);
+        assert!(result.results.keys().all(|k| result.is_valid_outcome(k)));
+        assert!(!result.is_valid_outcome("HHH"));
+        assert!((result.probability_at_least_k_heads(0) - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_to_json_string_in_generation_order() {
+        let result = run_with_symbols(2, 100, ['1', '0']);
+        let positions = |json: &str| -> Vec<usize> {
+            ["11", "10", "01", "00"]
+                .iter()
+                .map(|k| json.find(&format!("{k}: ")).unwrap())
+                .collect()
+        };
+
+        let generated = positions(&result.to_json_string_in_generation_order());
+        let sorted = positions(&result.to_json_string());
+
+        assert!(generated.windows(2).all(|w| w[0] < w[1]));
+        assert!(sorted.windows(2).all(|w| w[0] > w[1]));
+    }
+
+    #[test]
+    fn test_to_json_string_in_generation_order_matches_for_default_symbols() {
+        let result = run(3, 100);
+
+        assert_eq!(
+            result.to_json_string_in_generation_order(),
+            result.to_json_string()
+        );
+    }
+
+    #[test]
+    fn test_pattern_frequency_in_stream() {
+        let mut rng = rand::thread_rng();
+        let result = pattern_frequency_in_stream(20000, "HHH", &mut rng).unwrap();
+
+        assert!(result.count <= 20000 - 2);
+        assert!((result.probability - 0.125).abs() < 0.02);
+    }
+
+    #[test]
+    fn test_pattern_frequency_in_stream_whole_stream() {
+        let mut rng = rand::thread_rng();
+        let result = pattern_frequency_in_stream(3, "HTH", &mut rng).unwrap();
+
+        assert!(result.count <= 1);
+        assert_eq!(result.probability, result.count as f64);
+    }
+
+    #[test]
+    fn test_pattern_frequency_in_stream_validation() {
+        let mut rng = rand::thread_rng();
+
+        assert_eq!(
+            pattern_frequency_in_stream(2, "HHH", &mut rng).err(),
+            Some(SimError::PatternTooLong {
+                pattern_len: 3,
+                max_len: 2
+            })
+        );
+        assert_eq!(
+            pattern_frequency_in_stream(10, "HXH", &mut rng).err(),
+            Some(SimError::InvalidOutcome("HXH".to_string()))
+        );
+        assert_eq!(
+            pattern_frequency_in_stream(10, "", &mut rng).err(),
+            Some(SimError::InvalidOutcome(String::new()))
+        );
+    }
+
+    #[test]
+    fn test_run_with_bias_schedule_drifting() {
+        let iterations = 4000;
+        let result =
+            run_with_bias_schedule(4, iterations, |i| i as f64 / (iterations - 1) as f64).unwrap();
+
+        let heads: usize = result
+            .results
+            .iter()
+            .map(|(outcome, r)| num_heads(outcome) * r.count)
+            .sum();
+        let head_fraction = heads as f64 / (4 * iterations) as f64;
+
+        assert_eq!(result.iterations, iterations);
+        assert!((head_fraction - 0.5).abs() < 0.05);
+    }
+
+    #[test]
+    fn test_run_with_bias_schedule_invalid_bias() {
+        assert_eq!(
+            run_with_bias_schedule(2, 10, |i| if i < 5 { 0.5 } else { -0.1 }).err(),
+            Some(SimError::InvalidBias(-0.1))
+        );
+        assert!(run_with_bias_schedule(2, 10, |_| f64::NAN).is_err());
+    }
+
+    #[test]
+    fn test_empirical_result_display_default() {
+        let result = EmpiricalResult::new(1, 8);
+
+        assert_eq!(result.to_string(), "{count: 1, probability: 0.12500}");
+    }
+
+    #[test]
+    fn test_empirical_result_display_precision() {
+        let result = EmpiricalResult::new(1, 8);
+
+        assert_eq!(format!("{result:.2}"), "{count: 1, probability: 0.12}");
+        assert_eq!(format!("{result:.0}"), "{count: 1, probability: 0}");
+    }
+
+    #[test]
+    fn test_empirical_result_display_width() {
+        let result = EmpiricalResult::new(1, 8);
+
+        assert_eq!(
+            format!("{result:>33.3}"),
+            "   {count: 1, probability: 0.125}"
+        );
+        assert_eq!(
+            format!("{result:<33.3}|"),
+            "{count: 1, probability: 0.125}   |"
+        );
+        assert_eq!(
+            format!("{result:*^33.3}"),
+            "*{count: 1, probability: 0.125}**"
+        );
+        assert_eq!(format!("{result:5.1}"), "{count: 1, probability: 0.1}");
+    }
+
+    #[test]
+    fn test_coin_flip_result_display_precision() {
+        let result = result_from_counts(1, &[("H", 1), ("T", 3)]);
+
+        assert_eq!(result.to_string(), result.to_json_string());
+        assert!(format!("{result:.2}").contains("H: {count: 1, probability: 0.25}"));
+        assert!(format!("{result:.2}").contains("expected: {count: 2, probability: 0.50}"));
+        assert!(format!("{result:>200}").starts_with(' '));
+    }
+
+    #[test]
+    fn test_expected_longest_run_reference_values() {
+        let reference = [
+            (0, 0.0),
+            (1, 0.5),
+            (2, 1.0),
+            (3, 1.375),
+            (4, 1.6875),
+            (5, 1.9375),
+            (6, 2.15625),
+        ];
+
+        for (n, expected) in reference {
+            assert!(
+                (expected_longest_run(n) - expected).abs() < 1e-12,
+                "n = {n}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_expected_longest_run_large_n() {
+        let n = 1024;
+
+        assert!((expected_longest_run(n) - ((n as f64).log2() - 2.0 / 3.0)).abs() < 0.1);
+    }
+
+    #[test]
+    fn test_run_markov_sticky_heads() {
+        let result = run_markov(5, 200, 1.0, 0.5, 1.0).unwrap();
+
+        assert_eq!(result.results["HHHHH"].count, 200);
+    }
+
+    #[test]
+    fn test_run_markov_independent_matches_fair() {
+        let result = run_markov(2, 8000, 0.5, 0.5, 0.5).unwrap();
+
+        for r in result.results.values() {
+            assert!((r.probability - 0.25).abs() < 0.03);
+        }
+    }
+
+    #[test]
+    fn test_run_markov_invalid_probability() {
+        assert_eq!(
+            run_markov(2, 10, 1.1, 0.5, 0.5).err(),
+            Some(SimError::InvalidBias(1.1))
+        );
+        assert_eq!(
+            run_markov(2, 10, 0.5, 0.5, -1.0).err(),
+            Some(SimError::InvalidBias(-1.0))
+        );
+    }
+
+    #[test]
+    fn test_sample_size_warning() {
+        let result = run(4, 79);
+        let warning = result.sample_size_warning().unwrap();
+
+        assert!(warning.contains("79 iterations"));
+        assert!(warning.contains("16 possible outcomes"));
+        assert!(warning.contains("at least 80"));
+    }
+
+    #[test]
+    fn test_sample_size_no_warning() {
+        assert_eq!(run(4, 80).sample_size_warning(), None);
+        assert_eq!(
+            run_without_replacement(2, 2, 30)
+                .unwrap()
+                .sample_size_warning(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_iter_with_expected() {
+        let result = result_from_counts(3, &[("HHH", 3), ("HTH", 2), ("TTT", 5)]);
+        let triples: Vec<(&String, &EmpiricalResult, f64)> = result.iter_with_expected().collect();
+
+        assert_eq!(triples.len(), 3);
+        assert_eq!(triples[0].0, "HHH");
+        assert_eq!(triples[0].1.count, 3);
+        assert!(triples.iter().all(|(_, _, expected)| *expected == 0.125));
+
+        let without_replacement = run_without_replacement(1, 1, 10).unwrap();
+        assert!(without_replacement
+            .iter_with_expected()
+            .all(|(_, _, expected)| expected == 0.5));
+    }
+
+    #[test]
+    fn test_estimate() {
+        let estimate = estimate(10, 5000);
+
+        assert_eq!(estimate.num_outcomes, Some(1024));
+        assert!(!estimate.overflows);
+        assert!(estimate.estimated_bytes.unwrap() >= 1024 * (10 + std::mem::size_of::<String>()));
+    }
+
+    #[test]
+    fn test_estimate_overflow() {
+        let flips_per_iteration = usize::BITS as usize;
+        let estimate = estimate(flips_per_iteration, 10);
+
+        assert!(estimate.overflows);
+        assert_eq!(estimate.num_outcomes, None);
+        assert_eq!(estimate.estimated_bytes, None);
+        assert!(!super::estimate(flips_per_iteration - 1, 10).overflows);
+        assert!(super::estimate(usize::MAX, 10).overflows);
+    }
+
+    #[test]
+    fn test_probability_of_across_modes() {
+        let result = run(2, 100);
+        assert_eq!(
+            result.probability_of("HT"),
+            result.results["HT"].probability
+        );
+
+        let result = run_without_replacement(1, 2, 100).unwrap();
+        assert_eq!(result.probability_of("HHT"), 0.0);
+        assert_eq!(
+            result.probability_of("THT"),
+            result.results["THT"].probability
+        );
+
+        let result = run_with_symbols(2, 100, ['1', '0']);
+        assert_eq!(
+            result.probability_of("01"),
+            result.results["01"].probability
+        );
+
+        let result = run_markov(2, 100, 1.0, 1.0, 1.0).unwrap();
+        assert_eq!(result.probability_of("HH"), 1.0);
+        assert_eq!(result.probability_of("TH"), 0.0);
+    }
+
+    #[test]
+    #[should_panic(expected = "not a valid outcome")]
+    fn test_probability_of_invalid_key() {
+        run_with_symbols(2, 100, ['1', '0']).probability_of("HT");
+    }
+
+    #[test]
+    fn test_to_latex_table() {
+        let result = run(3, 100);
+        let table = result.to_latex_table();
+        let lines: Vec<&str> = table.lines().collect();
+
+        assert_eq!(lines.first(), Some(&"\\begin{tabular}{lrr}"));
+        assert_eq!(lines.last(), Some(&"\\end{tabular}"));
+        assert_eq!(lines[2], "Outcome & Count & Probability \\\\");
+
+        let rows = lines.iter().filter(|line| line.ends_with("\\\\")).count();
+        assert_eq!(rows, 1 + result.results.len());
+
+        let hhh = &result.results["HHH"];
+        assert_eq!(
+            lines[4],
+            format!("HHH & {} & {:.5} \\\\", hhh.count, hhh.probability)
+        );
+    }
+
+    fn counts_of(result: &CoinFlipResult) -> Vec<(String, usize)> {
+        result
+            .results
+            .iter()
+            .map(|(k, v)| (k.clone(), v.count))
+            .collect()
+    }
+
+    #[test]
+    fn test_run_parallel_seeded_matches_sequential() {
+        let iterations = 3 * SEEDED_BLOCK_SIZE + 17;
+        let sequential = run_seeded(4, iterations, 1234);
+
+        for threads in [0, 1, 2, 3, 4, 5, 8, 16] {
+            let parallel = run_parallel_seeded(4, iterations, 1234, threads);
+
+            assert_eq!(
+                counts_of(&parallel),
+                counts_of(&sequential),
+                "{threads} threads"
+            );
+            assert_eq!(parallel.iterations, iterations);
+        }
+        assert_eq!(
+            counts_of(&run_seeded(4, SEEDED_BLOCK_SIZE, 1234)),
+            counts_of(&run_parallel_seeded(4, SEEDED_BLOCK_SIZE, 1234, 3))
+        );
+    }
+
+    #[test]
+    fn test_run_seeded_depends_on_seed() {
+        let total: usize = run_seeded(3, 100, 1)
+            .results
+            .values()
+            .map(|r| r.count)
+            .sum();
+
+        assert_eq!(total, 100);
+        assert_ne!(
+            counts_of(&run_seeded(8, 1000, 1)),
+            counts_of(&run_seeded(8, 1000, 2))
+        );
+        assert_eq!(
+            counts_of(&run_parallel_seeded(2, 0, 5, 0)),
+            counts_of(&run_seeded(2, 0, 5))
+        );
+    }
+
+    #[test]
+    fn test_chi_square() {
+        let uniform = result_from_counts(2, &[("HH", 25), ("HT", 25), ("TH", 25), ("TT", 25)]);
+        let skewed = result_from_counts(2, &[("HH", 40), ("HT", 30), ("TH", 20), ("TT", 10)]);
+
+        assert_eq!(uniform.chi_square(), 0.0);
+        assert!((skewed.chi_square() - 20.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_monte_carlo_pvalue_uniform() {
+        let mut rng = StdRng::seed_from_u64(3);
+        let uniform = result_from_counts(2, &[("HH", 25), ("HT", 25), ("TH", 25), ("TT", 25)]);
+
+        assert_eq!(uniform.monte_carlo_pvalue(200, &mut rng), 1.0);
+    }
+
+    #[test]
+    fn test_monte_carlo_pvalue_skewed() {
+        let mut rng = StdRng::seed_from_u64(3);
+        let skewed = result_from_counts(2, &[("HH", 70), ("HT", 10), ("TH", 10), ("TT", 10)]);
+
+        assert!(skewed.monte_carlo_pvalue(200, &mut rng) < 0.05);
+    }
+
+    #[test]
+    fn test_to_plot_points() {
+        let result = result_from_counts(2, &[("HH", 40), ("HT", 30), ("TH", 20), ("TT", 10)]);
+        let points = result.to_plot_points();
+
+        assert_eq!(points.len(), 4);
+        assert_eq!(points[0], ("HH".to_string(), 0.4));
+        assert_eq!(points[3], ("TT".to_string(), 0.1));
+    }
+
+    #[test]
+    fn test_to_plot_points_counts() {
+        let result = result_from_counts(2, &[("HH", 40), ("HT", 30), ("TH", 20), ("TT", 10)]);
+        let points = result.to_plot_points_counts();
+
+        assert_eq!(points.len(), 4);
+        assert_eq!(points[0], ("HH".to_string(), 40.0));
+    }
+
+    #[test]
+    fn test_run_with_flip_budget_not_divisible() {
+        let (result, leftover) = run_with_flip_budget(4, 1003);
+
+        assert_eq!(result.iterations, 250);
+        assert_eq!(leftover, 3);
+        assert_eq!(result.results.values().map(|r| r.count).sum::<usize>(), 250);
+    }
+
+    #[test]
+    fn test_run_with_flip_budget_divisible() {
+        let (result, leftover) = run_with_flip_budget(2, 100);
+
+        assert_eq!(result.iterations, 50);
+        assert_eq!(leftover, 0);
+    }
+
+    #[test]
+    fn test_run_with_sampler_fires() {
+        let mut completed = Vec::new();
+        let result = run_with_sampler(2, 1050, 100, |snapshot| {
+            let total: usize = snapshot.values().map(|r| r.count).sum();
+            let probability: f64 = snapshot.values().map(|r| r.probability).sum();
+
+            assert!((probability - 1.0).abs() < 1e-9);
+            completed.push(total);
+        });
+
+        assert_eq!(completed, (1..=10).map(|i| i * 100).collect::<Vec<_>>());
+        assert_eq!(result.iterations, 1050);
+    }
+
+    #[test]
+    fn test_run_with_sampler_never_fires() {
+        let mut fired = 0;
+        run_with_sampler(2, 100, 0, |_| fired += 1);
+        run_with_sampler(2, 100, 101, |_| fired += 1);
+
+        assert_eq!(fired, 0);
+    }
+
+    #[test]
+    fn test_chi_square_critical() {
+        assert!((chi_square_critical(7, 0.05) - 14.067).abs() < 0.05);
+        assert!((chi_square_critical(10, 0.05) - 18.307).abs() < 0.05);
+        assert!((chi_square_critical(7, 0.01) - 18.475).abs() < 0.05);
+        assert!((chi_square_critical(1, 0.05) - 3.841).abs() < 0.1);
+    }
+
+    #[test]
+    fn test_chi_square_critical_out_of_range() {
+        assert!(chi_square_critical(0, 0.05).is_nan());
+        assert!(chi_square_critical(3, 0.0).is_nan());
+        assert!(chi_square_critical(3, 1.0).is_nan());
+    }
+
+    #[test]
+    fn test_run_bitmask_matches_run() {
+        let counts = run_bitmask(3, 8000);
+        let result = run(3, 8000);
+
+        let outcomes: Vec<String> = counts
+            .keys()
+            .map(|&mask| bitmask_to_string(mask, 3))
+            .collect();
+        assert!(outcomes
+            .iter()
+            .all(|outcome| result.results.contains_key(outcome)));
+        assert_eq!(counts.values().sum::<usize>(), 8000);
+        for count in counts.values() {
+            assert!(count.abs_diff(result.expected.count) < 200);
+        }
+    }
+
+    #[test]
+    fn test_run_bitmask_edge_sizes() {
+        assert_eq!(run_bitmask(0, 10), HashMap::from([(0, 10)]));
+        assert_eq!(run_bitmask(64, 10).values().sum::<usize>(), 10);
+        assert_eq!(bitmask_to_string(0, 0), "");
+        assert_eq!(bitmask_to_string(u64::MAX, 64), "H".repeat(64));
+    }
+
+    #[test]
+    fn test_distinct_observed_matches_expected() {
+        let result = run(4, 10_000);
+
+        assert_eq!(result.distinct_observed(), 16);
+        assert!((expected_distinct_outcomes(4, 10_000) - 16.0).abs() < 1e-9);
+        assert_eq!(expected_distinct_outcomes(4, 0), 0.0);
+    }
+
+    #[test]
+    fn test_distinct_observed_partial() {
+        let result = result_from_counts(2, &[("HH", 3), ("HT", 0), ("TH", 1), ("TT", 0)]);
+
+        assert_eq!(result.distinct_observed(), 2);
+    }
+
+    #[test]
+    fn test_run_waiting_time_summary_mean() {
+        let mut rng = StdRng::seed_from_u64(11);
+        let summary = run_waiting_time_summary("HT", 20000, &mut rng).unwrap();
+
+        assert_eq!(summary.trials, 20000);
+        assert!((summary.mean - 4.0).abs() < 0.1);
+        assert!(summary.median >= 2.0 && summary.median <= summary.p95);
+    }
+
+    #[test]
+    fn test_run_waiting_time_summary_invalid() {
+        let mut rng = StdRng::seed_from_u64(11);
+
+        assert_eq!(
+            run_waiting_time_summary("HX", 10, &mut rng).unwrap_err(),
+            SimError::InvalidOutcome("HX".to_string())
+        );
+        assert_eq!(
+            run_waiting_time_summary("H", 0, &mut rng).unwrap_err(),
+            SimError::ZeroIterations
+        );
+    }
+
+    #[test]
+    fn test_p2_quantile() {
+        let mut rng = StdRng::seed_from_u64(5);
+        let mut values: Vec<f64> = (1..=10000).map(f64::from).collect();
+        values.shuffle(&mut rng);
+
+        let mut median = P2Quantile::new(0.5);
+        let mut p95 = P2Quantile::new(0.95);
+        for &value in &values {
+            median.observe(value);
+            p95.observe(value);
+        }
+
+        assert!((median.estimate() - 5000.0).abs() < 100.0);
+        assert!((p95.estimate() - 9500.0).abs() < 100.0);
+    }
+
+    #[test]
+    fn test_p2_quantile_few_observations() {
+        let mut median = P2Quantile::new(0.5);
+        assert!(median.estimate().is_nan());
+
+        [3.0, 1.0, 2.0].iter().for_each(|&x| median.observe(x));
+        assert_eq!(median.estimate(), 2.0);
+    }
+
+    #[test]
+    fn test_retain_prunes_zero_counts() {
+        let mut result = result_from_counts(2, &[("HH", 3), ("HT", 0), ("TH", 1), ("TT", 0)]);
+        result.retain(|_, r| r.count > 0);
+
+        assert_eq!(
+            counts_of(&result),
+            vec![("HH".to_string(), 3), ("TH".to_string(), 1)]
+        );
+        assert_eq!(result.iterations, 4);
+    }
+
+    #[test]
+    fn test_covariance_with_head_count_is_variance() {
+        let result = result_from_counts(2, &[("HH", 40), ("HT", 30), ("TH", 20), ("TT", 10)]);
+        let variance = result.covariance_with(|outcome| outcome.matches('H').count() as f64);
+
+        assert!((variance - 0.41).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_covariance_with_first_head_position() {
+        let result = result_from_counts(2, &[("HH", 25), ("HT", 25), ("TH", 25), ("TT", 25)]);
+        let first_head = |outcome: &str| outcome.find('H').map_or(2.0, |i| i as f64);
+
+        assert!((result.covariance_with(first_head) + 0.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_outcome_to_index_matches_get_all_outcomes() {
+        for (i, outcome) in get_all_outcomes(4).iter().enumerate() {
+            assert_eq!(outcome_to_index(outcome), Some(i));
+        }
+        assert_eq!(outcome_to_index(""), Some(0));
+        assert_eq!(outcome_to_index(&"H".repeat(64)), None);
+    }
+
+    #[test]
+    fn test_iter_by_index_with_custom_symbols() {
+        let result = run_with_symbols(2, 100, ['1', '0']);
+        let by_index: Vec<&String> = result.iter_by_index().map(|(outcome, _)| outcome).collect();
+        let sorted: Vec<&String> = result.results.keys().collect();
+
+        assert_eq!(by_index, ["11", "10", "01", "00"]);
+        assert_eq!(sorted, ["00", "01", "10", "11"]);
+    }
+
+    #[test]
+    fn test_average_with_differs_from_summing() {
+        let small = result_from_counts(1, &[("H", 10), ("T", 0)]);
+        let large = result_from_counts(1, &[("H", 0), ("T", 90)]);
+
+        let average = small.average_with(&large).unwrap();
+        assert_eq!(average["H"], 0.5);
+        assert_eq!(average["T"], 0.5);
+
+        let mut accumulator = ResultAccumulator::new(1);
+        accumulator.add_result(&small).unwrap();
+        accumulator.add_result(&large).unwrap();
+        let summed = accumulator.finalize();
+        assert_eq!(summed.results["H"].probability, 0.1);
+        assert_eq!(summed.results["T"].probability, 0.9);
+    }
+
+    #[test]
+    fn test_average_with_flips_mismatch() {
+        let error = run(2, 10).average_with(&run(3, 10)).unwrap_err();
+
+        assert_eq!(
+            error,
+            MergeError::FlipsMismatch {
+                expected: 2,
+                found: 3
+            }
+        );
+    }
+
+    #[test]
+    fn test_validate_valid() {
+        assert_eq!(run(4, 1000).validate(), Ok(()));
+        assert_eq!(run(2, 0).validate(), Ok(()));
+    }
+
+    #[test]
+    fn test_validate_corrupted() {
+        let mut miscounted = result_from_counts(1, &[("H", 6), ("T", 4)]);
+        miscounted.results.get_mut("H").unwrap().count = 7;
+
+        let mut misweighted = result_from_counts(1, &[("H", 6), ("T", 4)]);
+        misweighted.results.get_mut("T").unwrap().probability = 0.5;
+
+        assert_eq!(
+            miscounted.validate(),
+            Err("counts sum to 11 but there were 10 iterations".to_string())
+        );
+        assert_eq!(
+            misweighted.validate(),
+            Err("probabilities sum to 1.1 instead of 1".to_string())
+        );
+    }
+
+    #[test]
+    fn test_run_with_kind() {
+        for kind in [RngKind::Fast, RngKind::Crypto] {
+            let result = run_with_kind(3, 8000, kind);
+            let total: f64 = result.results.values().map(|r| r.probability).sum();
+
+            assert_eq!(result.results.len(), 8);
+            assert!((total - 1.0).abs() < 1e-9);
+            assert_eq!(result.validate(), Ok(()));
+        }
+    }
+
+    #[test]
+    fn test_mean_run_length() {
+        let result = result_from_counts(2, &[("HH", 40), ("HT", 30), ("TH", 20), ("TT", 10)]);
+        let longer = result_from_counts(4, &[("HHTT", 1), ("HTHT", 1)]);
+
+        assert!((result.mean_run_length() - 1.5).abs() < 1e-9);
+        assert!((longer.mean_run_length() - 1.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_to_json_string_with_expected_keys_match() {
+        let result = result_from_counts(2, &[("HH", 40), ("HT", 30), ("TH", 20), ("TT", 10)]);
+        let json = result.to_json_string_with_expected();
+        let keys_of = |section: &str| -> Vec<String> {
+            let start = json.find(&format!("{section}: {{\n")).unwrap();
+            json[start..]
+                .lines()
+                .skip(1)
+                .take_while(|line| line.trim() != "}")
+                .map(|line| line.trim().split(':').next().unwrap().to_string())
+                .collect()
+        };
+
+        assert_eq!(keys_of("expected"), keys_of("actual"));
+        assert_eq!(keys_of("expected"), ["HH", "HT", "TH", "TT"]);
+        assert!(json.contains("        HH: 0.25000\n"));
+        assert!(json.contains("        HH: 0.40000\n"));
+    }
+
+    #[test]
+    fn test_run_with_first_seen_rare_outcomes_unseen() {
+        let (result, first_seen) = run_with_first_seen(10, 10);
+        let seen: Vec<usize> = first_seen.values().flatten().copied().collect();
+
+        assert_eq!(first_seen.len(), 1024);
+        assert_eq!(seen.len(), result.distinct_observed());
+        assert!(first_seen.values().filter(|index| index.is_none()).count() >= 1014);
+        assert_eq!(seen.iter().filter(|&&index| index == 0).count(), 1);
+        for (outcome, index) in first_seen {
+            assert_eq!(index.is_some(), result.results[&outcome].count > 0);
+        }
+    }
+
+    #[test]
+    fn test_run_majority_odd_flips() {
+        let heads_wins = run_majority(5, 20000, TieRule::HeadsWins);
+        let tails_wins = run_majority(5, 20000, TieRule::TailsWins);
+
+        assert!((heads_wins.probability - 0.5).abs() < 0.02);
+        assert!((tails_wins.probability - 0.5).abs() < 0.02);
+    }
+
+    #[test]
+    fn test_run_majority_even_flips_tie_rules() {
+        let heads_wins = run_majority(2, 20000, TieRule::HeadsWins);
+        let tails_wins = run_majority(2, 20000, TieRule::TailsWins);
+
+        assert!((heads_wins.probability - 0.75).abs() < 0.02);
+        assert!((tails_wins.probability - 0.25).abs() < 0.02);
+        assert_eq!(run_majority(0, 10, TieRule::HeadsWins).count, 10);
+        assert_eq!(run_majority(0, 10, TieRule::TailsWins).count, 0);
+    }
+
+    #[test]
+    fn test_into_iterator() {
+        let result = result_from_counts(2, &[("HH", 40), ("HT", 30), ("TH", 20), ("TT", 10)]);
+
+        let mut borrowed = 0;
+        for (_, empirical) in &result {
+            borrowed += empirical.count;
+        }
+
+        let owned: Vec<(String, EmpiricalResult)> = result.into_iter().collect();
+        assert_eq!(borrowed, 100);
+        assert_eq!(owned.len(), 4);
+        assert_eq!(owned[0].0, "HH");
+        assert_eq!(owned[0].1.count, 40);
+    }
+
+    #[test]
+    fn test_run_until_count_common_before_rare() {
+        let mut rng = StdRng::seed_from_u64(21);
+        let common = run_until_count(1, "H", 50, &mut rng).unwrap();
+        let rare = run_until_count(4, "HHHH", 50, &mut rng).unwrap();
+
+        assert_eq!(common.results["H"].count, 50);
+        assert_eq!(rare.results["HHHH"].count, 50);
+        assert!(common.iterations < rare.iterations);
+        assert_eq!(rare.validate(), Ok(()));
+    }
+
+    #[test]
+    fn test_run_until_count_invalid_target() {
+        let mut rng = StdRng::seed_from_u64(21);
+
+        for target in ["HT", "HXH", "HTHT"] {
+            assert_eq!(
+                run_until_count(3, target, 1, &mut rng).err(),
+                Some(SimError::InvalidOutcome(target.to_string()))
+            );
+        }
+        assert_eq!(
+            run_until_count(3, "HTH", 0, &mut rng).unwrap().iterations,
+            0
+        );
+    }
+
+    #[test]
+    fn test_mse_near_uniform_vs_skewed() {
+        let near_uniform = result_from_counts(2, &[("HH", 26), ("HT", 24), ("TH", 25), ("TT", 25)]);
+        let skewed = result_from_counts(2, &[("HH", 40), ("HT", 30), ("TH", 20), ("TT", 10)]);
+
+        assert!((near_uniform.mse() - 0.00005).abs() < 1e-12);
+        assert!((skewed.mse() - 0.0125).abs() < 1e-12);
+    }
+
+    #[cfg(feature = "plotters")]
+    #[test]
+    fn test_save_bar_chart_creates_file() {
+        let result = result_from_counts(2, &[("HH", 40), ("HT", 30), ("TH", 20), ("TT", 10)]);
+        let dir = std::env::temp_dir();
+        let path = dir.join(format!("coin_flip_bar_chart_{}.png", std::process::id()));
+        let top_path = dir.join(format!(
+            "coin_flip_bar_chart_top_{}.png",
+            std::process::id()
+        ));
+
+        result.save_bar_chart(path.to_str().unwrap()).unwrap();
+        result
+            .save_bar_chart_top_n(top_path.to_str().unwrap(), 2)
+            .unwrap();
+
+        assert!(std::fs::metadata(&path).unwrap().len() > 0);
+        assert!(std::fs::metadata(&top_path).unwrap().len() > 0);
+        std::fs::remove_file(path).unwrap();
+        std::fs::remove_file(top_path).unwrap();
+    }
+
+    #[test]
+    fn test_compare_to_reference_uniform_matches_built_in() {
+        let result = result_from_counts(2, &[("HH", 40), ("HT", 30), ("TH", 20), ("TT", 10)]);
+        let reference: BTreeMap<String, f64> =
+            get_all_outcomes(2).into_iter().map(|k| (k, 0.25)).collect();
+        let stats = result.compare_to_reference(&reference).unwrap();
+
+        let kl_divergence: f64 = [0.4f64, 0.3, 0.2, 0.1]
+            .iter()
+            .map(|p| p * (p / 0.25).ln())
+            .sum();
+        assert!((stats.chi_square - result.chi_square()).abs() < 1e-9);
+        assert!((stats.kl_divergence - kl_divergence).abs() < 1e-12);
+        assert!((stats.total_variation - 0.2).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_compare_to_reference_key_mismatch() {
+        let result = result_from_counts(1, &[("H", 6), ("T", 4)]);
+        let missing = BTreeMap::from([("H".to_string(), 1.0)]);
+        let extra = BTreeMap::from([
+            ("H".to_string(), 0.4),
+            ("T".to_string(), 0.4),
+            ("X".to_string(), 0.2),
+        ]);
+
+        assert_eq!(
+            result.compare_to_reference(&missing),
+            Err(SimError::InvalidOutcome("T".to_string()))
+        );
+        assert_eq!(
+            result.compare_to_reference(&extra),
+            Err(SimError::InvalidOutcome("X".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_run_antithetic_balances_heads() {
+        let mut rng = StdRng::seed_from_u64(8);
+        let result = run_antithetic(5, 1000, &mut rng).unwrap();
+        let total_heads: usize = result
+            .results
+            .iter()
+            .map(|(outcome, r)| num_heads(outcome) * r.count)
+            .sum();
+
+        assert_eq!(total_heads, 5 * 1000 / 2);
+        assert_eq!(result.validate(), Ok(()));
+    }
+
+    #[test]
+    fn test_run_antithetic_odd_iterations() {
+        let mut rng = StdRng::seed_from_u64(8);
+
+        assert_eq!(
+            run_antithetic(2, 7, &mut rng).err(),
+            Some(SimError::OddIterations(7))
+        );
+    }
+
+    #[test]
+    fn test_wilson_interval_zero_count() {
+        let result = result_from_counts(1, &[("H", 0), ("T", 100)]);
+        let (low, high) = result.results["H"].wilson_interval(100, 1.96);
+
+        assert_eq!(low, 0.0);
+        assert!((high - 0.03699).abs() < 1e-4);
+
+        let (low, high) = result.results["T"].wilson_interval(100, 1.96);
+        assert!((low - 0.96301).abs() < 1e-4);
+        assert!((high - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_wilson_interval_contains_probability() {
+        let result = result_from_counts(1, &[("H", 30), ("T", 70)]);
+        let (low, high) = result.results["H"].wilson_interval(100, 1.96);
+
+        assert!((low - 0.2189).abs() < 1e-4);
+        assert!((high - 0.3958).abs() < 1e-4);
+        assert_eq!(result.results["H"].wilson_interval(0, 1.96), (0.0, 1.0));
+    }
+
+    #[test]
+    fn test_try_run_ok() {
+        let result = try_run(3, 800).unwrap();
+
+        assert_eq!(result.iterations, 800);
+        assert_eq!(result.results.len(), 8);
+    }
+
+    #[test]
+    fn test_try_run_failures() {
+        let flips_per_iteration = usize::BITS as usize;
+
+        assert_eq!(
+            try_run(flips_per_iteration, 10).err(),
+            Some(SimError::Overflow {
+                flips_per_iteration
+            })
+        );
+        assert_eq!(try_run(3, 0).err(), Some(SimError::ZeroIterations));
+    }
+
+    #[test]
+    fn test_expected_substring_occurrences() {
+        assert_eq!(expected_substring_occurrences(3, "HH", 0.5), Ok(0.5));
+        assert_eq!(expected_substring_occurrences(3, "HTH", 0.5), Ok(0.125));
+        assert!((expected_substring_occurrences(4, "HH", 0.8).unwrap() - 1.92).abs() < 1e-12);
+        assert_eq!(
+            expected_substring_occurrences(2, "HHH", 0.5),
+            Err(SimError::PatternTooLong {
+                pattern_len: 3,
+                max_len: 2
+            })
+        );
+        assert_eq!(
+            expected_substring_occurrences(3, "HH", 1.5),
+            Err(SimError::InvalidBias(1.5))
+        );
+    }
+
+    #[test]
+    fn test_mean_substring_occurrences_overlapping() {
+        let counts: Vec<(String, usize)> =
+            get_all_outcomes(3).into_iter().map(|k| (k, 1)).collect();
+        let counts: Vec<(&str, usize)> = counts.iter().map(|(k, v)| (k.as_str(), *v)).collect();
+        let uniform = result_from_counts(3, &counts);
+        let all_heads = result_from_counts(3, &[("HHH", 10)]);
+
+        assert_eq!(uniform.mean_substring_occurrences("HH"), 0.5);
+        assert_eq!(all_heads.mean_substring_occurrences("HH"), 2.0);
+    }
+
+    #[test]
+    fn test_run_walk_lengths_and_parity() {
+        let walks = run_walk(7, 200);
+
+        assert_eq!(walks.len(), 200);
+        for walk in walks {
+            assert_eq!(walk.len(), 7);
+            assert!(walk.windows(2).all(|step| (step[1] - step[0]).abs() == 1));
+            assert_eq!(walk[0].abs(), 1);
+            assert_eq!(walk[6].rem_euclid(2), 1);
+        }
+        assert!(run_walk(0, 3).iter().all(|walk| walk.is_empty()));
+    }
+
+    #[test]
+    fn test_empirical_result_combine() {
+        let first = EmpiricalResult::new(30, 100);
+        let second = EmpiricalResult::new(10, 100);
+        let combined = first.combine(&second, 200);
+
+        assert_eq!(combined.count, 40);
+        assert_eq!(combined.probability, 0.2);
+        assert_eq!(first.combine(&second, 50).probability, 0.8);
+    }
+
+    #[test]
+    fn test_expected_count_variance_uniform() {
+        assert_eq!(expected_count_variance(3, 8000, None), 875.0);
+        assert_eq!(expected_count_variance(3, 8000, Some(0.125)), 875.0);
+        assert!((expected_count_variance(1, 100, Some(0.9)) - 9.0).abs() < 1e-9);
+        assert_eq!(expected_count_variance(0, 100, None), 0.0);
+    }
+
+    #[test]
+    fn test_to_json_string_with_partial_labels() {
+        let result = result_from_counts(2, &[("HH", 40), ("HT", 30), ("TH", 20), ("TT", 10)]);
+        let labels = BTreeMap::from([
+            ("HH".to_string(), "Double".to_string()),
+            ("TT".to_string(), "Bust".to_string()),
+        ]);
+        let json = result.to_json_string_with_labels(&labels);
+
+        assert!(json.contains("        Double: {count: 40"));
+        assert!(json.contains("        Bust: {count: 10"));
+        assert!(json.contains("        HT: {count: 30"));
+        assert!(!json.contains("HH:"));
+        assert_eq!(
+            result.to_json_string_with_labels(&BTreeMap::new()),
+            result.to_json_string()
+        );
+    }
+
+    #[test]
+    fn test_anomalies_flags_injected_outcome() {
+        let mut counts: Vec<(String, usize)> =
+            get_all_outcomes(3).into_iter().map(|k| (k, 1000)).collect();
+        counts[0].1 = 1300;
+        let counts: Vec<(&str, usize)> = counts.iter().map(|(k, v)| (k.as_str(), *v)).collect();
+        let result = result_from_counts(3, &counts);
+
+        assert_eq!(result.anomalies(0.05), ["HHH"]);
+        assert!(result_from_counts(1, &[("H", 52), ("T", 48)])
+            .anomalies(0.05)
+            .is_empty());
+    }
+
+    #[test]
+    fn test_outcomes_with_k_heads() {
+        let outcomes = outcomes_with_k_heads(4, 2);
+        let filtered: Vec<String> = get_all_outcomes(4)
+            .into_iter()
+            .filter(|o| num_heads(o) == 2)
+            .collect();
+
+        assert_eq!(outcomes.len(), 6);
+        assert_eq!(outcomes, filtered);
+        assert_eq!(outcomes_with_k_heads(4, 0), vec!["TTTT"]);
+        assert_eq!(outcomes_with_k_heads(0, 0), vec![""]);
+        assert!(outcomes_with_k_heads(3, 4).is_empty());
+    }
+
+    #[test]
+    fn test_run_with_edge_rate() {
+        let result = run_with_edge(5, 4000, 0.1).unwrap();
+        let edges: usize = result
+            .results
+            .iter()
+            .map(|(outcome, r)| outcome.matches('E').count() * r.count)
+            .sum();
+        let expected_total: f64 = result.iter_with_expected().map(|(_, _, p)| p).sum();
+
+        assert_eq!(result.results.len(), 243);
+        assert!((edges as f64 / 20000.0 - 0.1).abs() < 0.01);
+        assert!((expected_total - 1.0).abs() < 1e-9);
+        assert_eq!(result.validate(), Ok(()));
+    }
+
+    #[test]
+    fn test_run_with_edge_expected_per_outcome() {
+        let result = run_with_edge(2, 10, 0.2).unwrap();
+        let expected: BTreeMap<&String, f64> = result
+            .iter_with_expected()
+            .map(|(k, _, p)| (k, p))
+            .collect();
+
+        assert!((expected[&"EE".to_string()] - 0.04).abs() < 1e-12);
+        assert!((expected[&"HE".to_string()] - 0.08).abs() < 1e-12);
+        assert!((expected[&"HT".to_string()] - 0.16).abs() < 1e-12);
+        assert_eq!(
+            run_with_edge(2, 10, -0.1).err(),
+            Some(SimError::InvalidBias(-0.1))
+        );
+    }
+
+    #[test]
+    fn test_run_with_edge_too_many_flips() {
+        assert_eq!(
+            run_with_edge(20, 10, 0.1).err(),
+            Some(SimError::TooLarge {
+                flips_per_iteration: 20
+            })
+        );
+        assert_eq!(
+            run_with_edge(41, 10, 0.1).err(),
+            Some(SimError::Overflow {
+                flips_per_iteration: 41
+            })
+        );
+    }
+
+    #[test]
+    fn test_write_csv_rows() {
+        let result = result_from_counts(2, &[("HH", 40), ("HT", 30), ("TH", 20), ("TT", 10)]);
+        let mut buffer = Vec::new();
+        result.write_csv(&mut buffer).unwrap();
+
+        let csv = String::from_utf8(buffer).unwrap();
+        let rows: Vec<&str> = csv.lines().collect();
+        assert_eq!(rows.len(), 5);
+        assert_eq!(rows[0], "outcome,count,probability");
+        assert_eq!(rows[1], "HH,40,0.4");
+        assert_eq!(csv, result.to_csv_string());
+    }
+
+    #[test]
+    fn test_expected_wait_time_known_values() {
+        assert_eq!(expected_wait_time("H"), Ok(2.0));
+        assert_eq!(expected_wait_time("HT"), Ok(4.0));
+        assert_eq!(expected_wait_time("HH"), Ok(6.0));
+        assert_eq!(expected_wait_time("HTH"), Ok(10.0));
+        assert_eq!(expected_wait_time("HHH"), Ok(14.0));
+        assert_eq!(
+            expected_wait_time(""),
+            Err(SimError::InvalidOutcome(String::new()))
+        );
+    }
+
+    #[test]
+    fn test_expected_wait_time_matches_simulation() {
+        let mut rng = StdRng::seed_from_u64(13);
+        let summary = run_waiting_time_summary("HTH", 20000, &mut rng).unwrap();
+
+        assert!((summary.mean - expected_wait_time("HTH").unwrap()).abs() < 0.3);
+    }
+
+    #[test]
+    fn test_run_projected_single_position() {
+        let result = run_projected(6, &[3], 10000).unwrap();
+
+        assert_eq!(counts_of(&result).len(), 2);
+        assert_eq!(result.expected_probability(), 0.5);
+        assert!((result.results["H"].probability - 0.5).abs() < 0.03);
+        assert_eq!(result.validate(), Ok(()));
+    }
+
+    #[test]
+    fn test_run_projected_invalid_positions() {
+        assert_eq!(
+            run_projected(3, &[0, 3], 10).err(),
+            Some(SimError::InvalidPosition {
+                position: 3,
+                flips_per_iteration: 3
+            })
+        );
+        assert_eq!(
+            run_projected(3, &[1, 1], 10).err(),
+            Some(SimError::InvalidPosition {
+                position: 1,
+                flips_per_iteration: 3
+            })
+        );
+    }
+
+    #[test]
+    fn test_joint_pattern_probability_long_sequence() {
+        let mut rng = StdRng::seed_from_u64(17);
+        let p = joint_pattern_probability(40, 10000, "HHHH", "TTTT", &mut rng).unwrap();
+
+        assert!(p.joint <= p.marginal_a.min(p.marginal_b));
+        assert!((p.marginal_a - p.marginal_b).abs() < 0.05);
+        assert!((p.joint - p.marginal_a * p.marginal_b).abs() < 0.05);
+    }
+
+    #[test]
+    fn test_joint_pattern_probability_invalid() {
+        let mut rng = StdRng::seed_from_u64(17);
+
+        assert_eq!(
+            joint_pattern_probability(3, 10, "HT", "HTHT", &mut rng),
+            Err(SimError::PatternTooLong {
+                pattern_len: 4,
+                max_len: 3
+            })
+        );
+        assert_eq!(
+            joint_pattern_probability(3, 10, "", "H", &mut rng),
+            Err(SimError::InvalidOutcome(String::new()))
+        );
+        assert_eq!(
+            joint_pattern_probability(3, 0, "H", "T", &mut rng),
+            Err(SimError::ZeroIterations)
+        );
+    }
+
+    #[test]
+    fn test_clone_is_independent() {
+        let original = result_from_counts(2, &[("HH", 3), ("HT", 0), ("TH", 1), ("TT", 0)]);
+        let mut pruned = original.clone();
+        pruned.retain(|_, r| r.count > 0);
+        pruned.results.get_mut("HH").unwrap().count = 99;
+
+        let copied = original.results["TH"];
+        assert_eq!(copied.count, 1);
+        assert_eq!(pruned.results.len(), 2);
+        assert_eq!(
+            counts_of(&original),
+            vec![
+                ("HH".to_string(), 3),
+                ("HT".to_string(), 0),
+                ("TH".to_string(), 1),
+                ("TT".to_string(), 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_summary_matches_individual_methods() {
+        let result = result_from_counts(2, &[("HH", 40), ("HT", 30), ("TH", 20), ("TT", 10)]);
+        let summary = result.summary();
+        let probabilities = [0.4f64, 0.3, 0.2, 0.1];
+
+        assert!((summary.chi_square - result.chi_square()).abs() < 1e-9);
+        assert!((summary.mse - result.mse()).abs() < 1e-12);
+        assert!(
+            (summary.heads_variance - result.covariance_with(|o| num_heads(o) as f64)).abs()
+                < 1e-12
+        );
+        assert!((summary.mean_heads - 1.3).abs() < 1e-12);
+        assert!(
+            (summary.entropy + probabilities.iter().map(|p| p * p.log2()).sum::<f64>()).abs()
+                < 1e-12
+        );
+        assert!(
+            (summary.kl_divergence
+                - probabilities
+                    .iter()
+                    .map(|p| p * (p / 0.25).ln())
+                    .sum::<f64>())
+            .abs()
+                < 1e-12
+        );
+    }
+
+    #[test]
+    fn test_summary_uniform() {
+        let summary =
+            result_from_counts(2, &[("HH", 25), ("HT", 25), ("TH", 25), ("TT", 25)]).summary();
+
+        assert_eq!(summary.entropy, 2.0);
+        assert_eq!(summary.kl_divergence, 0.0);
+        assert_eq!(summary.chi_square, 0.0);
+        assert_eq!(summary.heads_variance, 0.5);
+    }
+
+    #[test]
+    fn test_run_with_reservoir_length() {
+        let mut rng = StdRng::seed_from_u64(23);
+
+        for (iterations, sample_size) in [(1000, 100), (50, 100), (0, 10), (10, 0)] {
+            let (result, sample) = run_with_reservoir(4, iterations, sample_size, &mut rng);
+
+            assert_eq!(sample.len(), sample_size.min(iterations));
+            assert!(sample
+                .iter()
+                .all(|outcome| result.results[outcome].count > 0));
+        }
+    }
+
+    #[test]
+    fn test_estimated_json_len() {
+        let result = result_from_counts(2, &[("HH", 40), ("HT", 30), ("TH", 20), ("TT", 10)]);
+        let large = run(8, 100_000);
+
+        assert_eq!(result.estimated_json_len(), result.to_json_string().len());
+        assert_eq!(large.estimated_json_len(), large.to_json_string().len());
+        assert!(
+            run(2, 0)
+                .estimated_json_len()
+                .abs_diff(run(2, 0).to_json_string().len())
+                <= 25
+        );
+    }
+
+    #[test]
+    fn test_run_multi_coin_fair_and_always_heads() {
+        let result = run_multi_coin(&[0.5, 1.0], 4000).unwrap();
+        let expected: BTreeMap<&String, f64> = result
+            .iter_with_expected()
+            .map(|(k, _, p)| (k, p))
+            .collect();
+
+        assert_eq!(result.validate(), Ok(()));
+        assert_eq!(result.results["HT"].count, 0);
+        assert_eq!(result.results["TT"].count, 0);
+        assert!((result.results["HH"].probability - 0.5).abs() < 0.05);
+        assert_eq!(
+            expected.values().copied().collect::<Vec<f64>>(),
+            [0.5, 0.0, 0.5, 0.0]
+        );
+    }
+
+    #[test]
+    fn test_run_multi_coin_invalid_bias() {
+        assert_eq!(
+            run_multi_coin(&[0.5, 2.0], 10).err(),
+            Some(SimError::InvalidBias(2.0))
+        );
+    }
+
+    #[test]
+    fn test_ks_statistic_binomial() {
+        let counts: Vec<(String, usize)> =
+            get_all_outcomes(4).into_iter().map(|k| (k, 10)).collect();
+        let counts: Vec<(&str, usize)> = counts.iter().map(|(k, v)| (k.as_str(), *v)).collect();
+        let binomial = result_from_counts(4, &counts);
+
+        assert!(binomial.ks_statistic(0.5) < 1e-12);
+        assert!((binomial.ks_statistic(1.0) - 15.0 / 16.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_ks_statistic_near_binomial() {
+        let result = result_from_counts(2, &[("HH", 26), ("HT", 24), ("TH", 26), ("TT", 24)]);
+
+        assert!((result.ks_statistic(0.5) - 0.01).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_to_log_line() {
+        let result = result_from_counts(2, &[("HH", 40), ("HT", 30), ("TH", 20), ("TT", 10)]);
+        let line = result.to_log_line();
+        let fields: Vec<&str> = line.split(' ').collect();
+
+        assert_eq!(
+            fields,
+            [
+                "flips=2",
+                "iters=100",
+                "chi2=20.0",
+                "entropy=1.85",
+                "max_err=0.150"
+            ]
+        );
+        assert_eq!(line.lines().count(), 1);
+    }
+
+    #[test]
+    fn test_run_gamblers_ruin_fair() {
+        let result = run_gamblers_ruin(3, 10, 10000, 0.5).unwrap();
+
+        assert!((result.probability - 0.3).abs() < 0.03);
+        assert_eq!(run_gamblers_ruin(3, 10, 100, 1.0).unwrap().count, 100);
+        assert_eq!(run_gamblers_ruin(3, 10, 100, 0.0).unwrap().count, 0);
+    }
+
+    #[test]
+    fn test_run_gamblers_ruin_invalid() {
+        assert_eq!(
+            run_gamblers_ruin(0, 10, 1, 0.5).err(),
+            Some(SimError::InvalidBankroll {
+                start: 0,
+                target: 10
+            })
+        );
+        assert_eq!(
+            run_gamblers_ruin(10, 10, 1, 0.5).err(),
+            Some(SimError::InvalidBankroll {
+                start: 10,
+                target: 10
+            })
+        );
+        assert_eq!(
+            run_gamblers_ruin(3, 10, 1, 1.5).err(),
+            Some(SimError::InvalidBias(1.5))
+        );
+    }
+
+    #[test]
+    fn test_bucket_summary() {
+        let result = result_from_counts(2, &[("HH", 40), ("HT", 26), ("TH", 24), ("TT", 10)]);
+
+        assert_eq!(result.bucket_summary(0.1), (1, 2, 1));
+        assert_eq!(result.bucket_summary(0.0), (2, 0, 2));
+        assert_eq!(result.bucket_summary(1.0), (0, 4, 0));
+
+        let (over, on_target, under) = run(4, 1000).bucket_summary(0.15);
+        assert_eq!(over + on_target + under, 16);
+    }
+
+    struct FlushCounter {
+        bytes: Vec<u8>,
+        flushes: usize,
+    }
+
+    impl Write for FlushCounter {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.bytes.extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            self.flushes += 1;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_run_streaming_batched_flushes() {
+        for (iterations, batch_size, flushes) in
+            [(10, 4, 3), (8, 4, 2), (5, 1, 5), (5, 0, 5), (0, 4, 0)]
+        {
+            let mut writer = FlushCounter {
+                bytes: Vec::new(),
+                flushes: 0,
+            };
+            let result = run_streaming_batched(2, iterations, &mut writer, batch_size).unwrap();
+
+            assert_eq!(writer.flushes, flushes);
+            assert_eq!(
+                String::from_utf8(writer.bytes).unwrap().lines().count(),
+                iterations
+            );
+            assert_eq!(result.iterations, iterations);
+        }
+    }
+
+    #[test]
+    fn test_run_streaming_records() {
+        let mut ndjson = Vec::new();
+        let result = run_streaming(3, 4, &mut ndjson).unwrap();
+        let ndjson = String::from_utf8(ndjson).unwrap();
+
+        for (i, line) in ndjson.lines().enumerate() {
+            let outcome = line
+                .strip_prefix(&format!("{{\"iteration\":{i},\"outcome\":\""))
+                .and_then(|rest| rest.strip_suffix("\"}"))
+                .unwrap();
+            assert!(result.results[outcome].count > 0);
+        }
+    }
+
+    #[test]
+    fn test_odds() {
+        let result = result_from_counts(3, &[("HHH", 125), ("HHT", 500), ("HTH", 375), ("HTT", 0)]);
+
+        assert_eq!(result.results["HHH"].odds(1000), (1, 7));
+        assert_eq!(result.results["HHH"].odds_string(1000), "1:7");
+        assert_eq!(result.results["HHT"].odds_string(1000), "1:1");
+        assert_eq!(result.results["HTH"].odds_string(1000), "3:5");
+    }
+
+    #[test]
+    fn test_odds_edge_cases() {
+        let result = result_from_counts(1, &[("H", 0), ("T", 40)]);
+
+        assert_eq!(result.results["H"].odds(40), (0, 1));
+        assert_eq!(result.results["T"].odds(40), (1, 0));
+
+        let empty = result_from_counts(1, &[("H", 0), ("T", 0)]);
+        assert_eq!(empty.results["H"].odds(empty.iterations), (0, 1));
+
+        let count = (1usize << 60) + 1;
+        let large = EmpiricalResult::new(count, 2 * count);
+        assert_eq!(large.odds(2 * count), (1, 1));
+        assert_eq!(large.odds(3 * count), (1, 2));
+    }
+
+    #[test]
+    fn test_run_full_head_counts_agree() {
+        let full = run_full(4, 2000);
+        let mut derived = vec![0; 5];
+        for (outcome, result) in full.sequences.results.iter() {
+            derived[num_heads(outcome)] += result.count;
+        }
+
+        let head_counts: Vec<usize> = full.head_counts.values().map(|r| r.count).collect();
+        assert_eq!(head_counts, derived);
+        assert_eq!(head_counts.iter().sum::<usize>(), 2000);
+        let exactly_two = full.sequences.probability_at_least_k_heads(2)
+            - full.sequences.probability_at_least_k_heads(3);
+        assert!((full.head_counts[&2].probability - exactly_two).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_outcome_indexing_consistent() {
+        for flips_per_iteration in 0..=16 {
+            assert_eq!(
+                check_outcome_indexing(flips_per_iteration),
+                Ok(()),
+                "{flips_per_iteration} flips"
+            );
+        }
+    }
+
+    #[test]
+    fn test_set_expected_map_changes_errors() {
+        let mut result = result_from_counts(1, &[("H", 90), ("T", 10)]);
+        let uniform_chi_square = result.chi_square();
+        let uniform_mse = result.mse();
+        assert!((result.comparison()[0].abs_error - 0.4).abs() < 1e-12);
+
+        let expected = BTreeMap::from([("H".to_string(), 0.9), ("T".to_string(), 0.1)]);
+        result.set_expected_map(expected).unwrap();
+
+        assert!(uniform_chi_square > 60.0);
+        assert!(result.chi_square() < 1e-12);
+        assert!(uniform_mse > 0.1);
+        assert!(result.mse() < 1e-12);
+        assert!(result.comparison().iter().all(|c| c.abs_error < 1e-12));
+        assert!(result
+            .to_json_string_with_expected()
+            .contains("H: 0.90000\n        T: 0.10000"));
+
+        let mut rng = ChaCha20Rng::seed_from_u64(3);
+        assert!(result.monte_carlo_pvalue(200, &mut rng) > 0.5);
+    }
+
+    #[test]
+    fn test_builder_without_unobserved() {
+        let result = SimulationBuilder::new(12, 50)
+            .include_unobserved(false)
+            .run();
+
+        assert_eq!(result.results.len(), result.distinct_observed());
+        assert!(result.results.values().all(|r| r.count > 0));
+        assert!(result.unobserved().is_empty());
+        assert_eq!(result.results.values().map(|r| r.count).sum::<usize>(), 50);
+    }
+
+    #[test]
+    fn test_builder_with_unobserved() {
+        let result = SimulationBuilder::new(3, 2).run();
+
+        assert_eq!(result.results.len(), 8);
+        assert!(result.unobserved().len() >= 6);
+    }
+
+    #[test]
+    fn test_per_position_head_frequency() {
+        let result = result_from_counts(3, &[("HHH", 20), ("HTT", 50), ("THT", 30)]);
+        let frequencies = result.per_position_head_frequency();
+
+        let expected = [0.7, 0.5, 0.2];
+        for (frequency, expected) in frequencies.iter().zip(expected) {
+            assert!((frequency - expected).abs() < 1e-12);
+        }
+        assert_eq!(frequencies.len(), 3);
+    }
+
+    #[test]
+    fn test_expected_sup_deviation_shrinks() {
+        let bands: Vec<f64> = [100, 1000, 10000, 100000]
+            .iter()
+            .map(|&iterations| expected_sup_deviation(3, iterations, 0.95))
+            .collect();
+
+        assert!(bands.windows(2).all(|pair| pair[1] < pair[0]));
+        assert!((bands[0] / bands[2] - 10.0).abs() < 1e-9);
+        assert!(expected_sup_deviation(3, 1000, 0.99) > expected_sup_deviation(3, 1000, 0.9));
+        assert!(expected_sup_deviation(3, 1000, 1.0).is_nan());
+    }
+
+    #[test]
+    fn test_expected_sup_deviation_covers_run() {
+        let band = expected_sup_deviation(2, 4000, 0.999);
+        let result = run_seeded(2, 4000, 11);
+
+        for (_, observed, expected) in result.iter_with_expected() {
+            assert!((observed.probability - expected).abs() <= band);
+        }
+    }
+
+    #[test]
+    fn test_bootstrap_degenerate() {
+        let result = result_from_counts(2, &[("HH", 0), ("HT", 250), ("TH", 0), ("TT", 0)]);
+        let mut rng = ChaCha20Rng::seed_from_u64(5);
+        let resampled = result.bootstrap(&mut rng);
+
+        assert_eq!(counts_of(&resampled), counts_of(&result));
+        assert_eq!(resampled.iterations, 250);
+        assert_eq!(resampled.results["HT"].probability, 1.0);
+    }
+
+    #[test]
+    fn test_bootstrap_preserves_total() {
+        let result = result_from_counts(1, &[("H", 30), ("T", 70)]);
+        let mut rng = ChaCha20Rng::seed_from_u64(6);
+        let resampled = result.bootstrap(&mut rng);
+
+        assert_eq!(
+            resampled.results.values().map(|r| r.count).sum::<usize>(),
+            100
+        );
+        assert!((resampled.results["H"].probability - 0.3).abs() < 0.2);
+    }
+
+    #[test]
+    fn test_expected_value_single_payout() {
+        let result = result_from_counts(3, &[("HHH", 25), ("HHT", 75), ("TTT", 100)]);
+        let payouts = BTreeMap::from([("HHH".to_string(), 10.0)]);
+
+        assert!((result.expected_value(&payouts) - 1.25).abs() < 1e-12);
+        assert_eq!(result.expected_value(&BTreeMap::new()), 0.0);
+
+        let payouts = BTreeMap::from([("HHH".to_string(), 10.0), ("TTT".to_string(), -1.0)]);
+        assert!((result.expected_value(&payouts) - 0.75).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_zero_crossings_alternating() {
+        let flips = |outcome: &str| outcome.chars().map(|c| c == 'H').collect::<Vec<_>>();
+
+        assert_eq!(zero_crossings(flips("HTTHHTTH").into_iter()), 3);
+        assert_eq!(zero_crossings(flips("HTHTHTHT").into_iter()), 0);
+        assert_eq!(zero_crossings(flips("HHTTTTHH").into_iter()), 1);
+        assert_eq!(zero_crossings(flips("").into_iter()), 0);
+    }
+
+    #[test]
+    fn test_mean_zero_crossings() {
+        let mut rng = ChaCha20Rng::seed_from_u64(8);
+
+        assert_eq!(mean_zero_crossings(1, 100, &mut rng), 0.0);
+        assert_eq!(mean_zero_crossings(10, 0, &mut rng), 0.0);
+        assert!(mean_zero_crossings(50, 2000, &mut rng) > 0.5);
+    }
+
+    #[test]
+    fn test_from_counts_valid() {
+        let counts = BTreeMap::from([("HHT".to_string(), 6), ("THH".to_string(), 2)]);
+        let result = CoinFlipResult::from_counts(3, counts).unwrap();
+
+        assert_eq!(result.iterations, 8);
+        assert_eq!(result.results.len(), 8);
+        assert_eq!(result.results["HHT"].probability, 0.75);
+        assert_eq!(result.results["TTT"].count, 0);
+        assert_eq!(result.expected.count, 1);
+        assert_eq!(result.expected_probability(), 0.125);
+    }
+
+    #[test]
+    fn test_from_counts_invalid() {
+        let invalid_key = BTreeMap::from([("HH".to_string(), 1), ("HX".to_string(), 1)]);
+        let error = Some(SimError::InvalidOutcome("HX".to_string()));
+        assert_eq!(CoinFlipResult::from_counts(2, invalid_key).err(), error);
+
+        let wrong_length = BTreeMap::from([("HHH".to_string(), 1)]);
+        let error = Some(SimError::InvalidOutcome("HHH".to_string()));
+        assert_eq!(CoinFlipResult::from_counts(2, wrong_length).err(), error);
+
+        let empty = BTreeMap::from([("HH".to_string(), 0)]);
+        assert_eq!(
+            CoinFlipResult::from_counts(2, empty).err(),
+            Some(SimError::ZeroIterations)
+        );
+
+        let too_large = BTreeMap::from([("H".repeat(40), 1)]);
+        let error = Some(SimError::TooLarge {
+            flips_per_iteration: 40,
+        });
+        assert_eq!(CoinFlipResult::from_counts(40, too_large).err(), error);
+    }
+
+    #[test]
+    fn test_benchmark_smoke() {
+        let report = benchmark(8, 5000);
+
+        assert!(report.flips_per_sec > 0.0);
+        assert!(report.iterations_per_sec > 0.0);
+        assert!((report.flips_per_sec / report.iterations_per_sec - 8.0).abs() < 1e-9);
+        assert_eq!(Some(report.result_bytes), estimate(8, 5000).estimated_bytes);
+    }
+
+    #[test]
+    fn test_to_columns_aligned() {
+        let result = result_from_counts(2, &[("HH", 40), ("HT", 30), ("TH", 20), ("TT", 10)]);
+        let (outcomes, counts, probabilities) = result.to_columns();
+
+        assert_eq!(outcomes.len(), counts.len());
+        assert_eq!(counts.len(), probabilities.len());
+        for ((outcome, count), probability) in outcomes.iter().zip(&counts).zip(&probabilities) {
+            assert_eq!(result.results[outcome].count as u64, *count);
+            assert_eq!(result.results[outcome].probability, *probability);
+        }
+        assert_eq!(counts, [40, 30, 20, 10]);
+    }
+
+    #[test]
+    fn test_hamming_distance_distribution() {
+        let result = result_from_counts(3, &[("HHH", 10), ("HTH", 20), ("THT", 30), ("TTT", 40)]);
+        let distances = result.hamming_distance_distribution("HHH");
+
+        let expected = [0.1, 0.2, 0.3, 0.4];
+        assert_eq!(distances.len(), 4);
+        for (d, expected) in expected.iter().enumerate() {
+            assert!((distances[&d] - expected).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_hamming_distance_distribution_binomial() {
+        let distances = run_seeded(3, 8000, 9).hamming_distance_distribution("HHH");
+
+        for (d, binomial) in [0.125, 0.375, 0.375, 0.125].iter().enumerate() {
+            assert!((distances[&d] - binomial).abs() < 0.03);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "doesn't have 3 symbols")]
+    fn test_hamming_distance_distribution_wrong_length() {
+        run(3, 10).hamming_distance_distribution("HH");
+    }
+
+    #[test]
+    fn test_run_exhaustive_uniform() {
+        for flips_per_iteration in 0..=6 {
+            let result = run_exhaustive(flips_per_iteration);
+            let num_outcomes = get_num_outcomes(flips_per_iteration);
+
+            assert_eq!(result.iterations, num_outcomes);
+            assert_eq!(result.expected.count, 1);
+            for empirical in result.results.values() {
+                assert_eq!(empirical.probability, 1.0 / num_outcomes as f64);
+            }
+        }
+        assert_eq!(run_exhaustive(4).chi_square(), 0.0);
+    }
+
+    #[test]
+    fn test_probability_of_run() {
+        let result = result_from_counts(3, &[("HHH", 10), ("HHT", 20), ("HTH", 30), ("TTT", 40)]);
+
+        assert!((result.probability_of_run(3, None) - 0.5).abs() < 1e-12);
+        assert!((result.probability_of_run(3, Some('H')) - 0.1).abs() < 1e-12);
+        assert!((result.probability_of_run(2, Some('H')) - 0.3).abs() < 1e-12);
+        assert!((result.probability_of_run(2, None) - 0.7).abs() < 1e-12);
+        assert!((result.probability_of_run(1, Some('T')) - 0.9).abs() < 1e-12);
+        assert_eq!(result.probability_of_run(4, None), 0.0);
+    }
+
+    #[test]
+    fn test_probability_of_run_only_uniform_outcomes() {
+        let result = run_exhaustive(3);
+
+        assert_eq!(result.probability_of_run(3, None), 0.25);
+        assert_eq!(result.probability_of_run(3, Some('T')), 0.125);
+    }
+
+    #[test]
+    fn test_sample_degenerate() {
+        let result = result_from_counts(2, &[("HH", 0), ("HT", 0), ("TH", 12), ("TT", 0)]);
+        let mut rng = ChaCha20Rng::seed_from_u64(10);
+
+        for _ in 0..100 {
+            assert_eq!(result.sample(&mut rng), "TH");
+        }
+    }
+
+    #[test]
+    fn test_sample_follows_distribution() {
+        let result = result_from_counts(1, &[("H", 80), ("T", 20)]);
+        let mut rng = ChaCha20Rng::seed_from_u64(11);
+
+        let heads = (0..10000)
+            .filter(|_| result.sample(&mut rng) == "H")
+            .count();
+        assert!((heads as f64 / 10000.0 - 0.8).abs() < 0.02);
+    }
+
+    #[test]
+    fn test_effective_sample_size() {
+        let result = result_from_counts(1, &[("H", 600), ("T", 400)]);
+
+        assert_eq!(result.effective_sample_size(0.0), 1000.0);
+        assert!(result.effective_sample_size(0.2) < 1000.0);
+        assert!(result.effective_sample_size(0.6) < result.effective_sample_size(0.2));
+        assert!(result.effective_sample_size(-0.2) > 1000.0);
+        assert_eq!(result.effective_sample_size(1.0), 0.0);
+        assert!(result.effective_sample_size(-1.0).is_nan());
+    }
+
+    #[test]
+    fn test_two_sample_chi_square() {
+        let a = result_from_counts(2, &[("HH", 25), ("HT", 25), ("TH", 25), ("TT", 25)]);
+        let b = result_from_counts(2, &[("HH", 50), ("HT", 50), ("TH", 50), ("TT", 50)]);
+        assert_eq!(a.two_sample_chi_square(&b), Ok(0.0));
+
+        let c = result_from_counts(2, &[("HH", 27), ("HT", 24), ("TH", 26), ("TT", 23)]);
+        assert!(a.two_sample_chi_square(&c).unwrap() < 1.0);
+
+        let d = result_from_counts(2, &[("HH", 70), ("HT", 10), ("TH", 10), ("TT", 10)]);
+        let statistic = a.two_sample_chi_square(&d).unwrap();
+        assert!(statistic > chi_square_critical(3, 0.001));
+        assert!((d.two_sample_chi_square(&a).unwrap() - statistic).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_two_sample_chi_square_mismatch() {
+        let a = result_from_counts(2, &[("HH", 10), ("HT", 10)]);
+        let b = result_from_counts(2, &[("HH", 10), ("TT", 10)]);
+        assert_eq!(
+            a.two_sample_chi_square(&b),
+            Err(MergeError::OutcomeMismatch("HT".to_string()))
+        );
+        assert_eq!(
+            b.two_sample_chi_square(&a),
+            Err(MergeError::OutcomeMismatch("TT".to_string()))
+        );
+
+        let c = result_from_counts(1, &[("H", 10), ("T", 10)]);
+        assert_eq!(
+            a.two_sample_chi_square(&c),
+            Err(MergeError::FlipsMismatch {
+                expected: 2,
+                found: 1
+            })
+        );
+    }
+
+    #[test]
+    fn test_iter_by_index_edge() {
+        let result = run_with_edge(2, 100, 0.2).unwrap();
+        let outcomes: Vec<&String> = result.iter_by_index().map(|(outcome, _)| outcome).collect();
+
+        assert_eq!(
+            outcomes,
+            ["HH", "HT", "HE", "TH", "TT", "TE", "EH", "ET", "EE"]
+        );
+    }
+
+    #[test]
+    fn test_to_json_string_in_generation_order_edge() {
+        let result = run_with_edge(2, 100, 0.2).unwrap();
+        let json = result.to_json_string_in_generation_order();
+
+        let entries: Vec<&str> = json
+            .lines()
+            .filter(|line| line.starts_with("        "))
+            .collect();
+        assert_eq!(entries.len(), 9);
+        assert_eq!(entries.iter().filter(|line| line.contains('E')).count(), 5);
+        assert!(json.find("HE:").unwrap() < json.find("TH:").unwrap());
+    }
+
+    #[test]
+    fn test_run_without_replacement_large() {
+        let result = run_without_replacement(10, 10, 1).unwrap();
+
+        assert_eq!(result.results.len(), 184_756);
+        assert_eq!(result.expected_probability(), 1.0 / 184_756.0);
+        assert_eq!(result.results.values().map(|r| r.count).sum::<usize>(), 1);
+    }
+
+    #[test]
+    fn test_run_without_replacement_overflow() {
+        assert_eq!(
+            run_without_replacement(40, 40, 1).err(),
+            Some(SimError::Overflow {
+                flips_per_iteration: 80
+            })
+        );
+        assert_eq!(
+            run_without_replacement(usize::MAX, 1, 1).err(),
+            Some(SimError::Overflow {
+                flips_per_iteration: usize::MAX
+            })
+        );
+        let all_heads = run_without_replacement(70, 0, 4).unwrap();
+        assert_eq!(all_heads.results["H".repeat(70).as_str()].count, 4);
+    }
+
+    #[test]
+    fn test_binomial_coefficient() {
+        assert_eq!(binomial_coefficient(5, 2), Some(10));
+        assert_eq!(binomial_coefficient(24, 12), Some(2_704_156));
+        assert_eq!(binomial_coefficient(3, 4), Some(0));
+        assert_eq!(
+            binomial_coefficient(64, 32),
+            Some(1_832_624_140_942_590_534)
+        );
+        assert_eq!(binomial_coefficient(80, 40), None);
+    }
+
+    #[test]
+    fn test_num_outcomes_exact() {
+        assert_eq!(run(3, 10).num_outcomes(), 8);
+        assert_eq!(
+            result_from_counts(53, &[(&"H".repeat(53), 1)]).num_outcomes(),
+            1 << 53
+        );
+        assert_eq!(
+            run_without_replacement(10, 10, 1).unwrap().num_outcomes(),
+            184_756
+        );
+        assert_eq!(run_with_edge(3, 10, 0.1).unwrap().num_outcomes(), 27);
+
+        let result = run_without_replacement(2, 2, 10).unwrap();
+        assert_eq!(
+            CoinFlipResult::from_bytes(&result.to_bytes())
+                .unwrap()
+                .num_outcomes(),
+            6
+        );
+    }
+
+    #[test]
+    fn test_monte_carlo_pvalue_no_resamples() {
+        let mut rng = ChaCha20Rng::seed_from_u64(12);
+
+        assert!(run(2, 100).monte_carlo_pvalue(0, &mut rng).is_nan());
+    }
+
+    #[test]
+    fn test_monte_carlo_pvalue_pruned() {
+        let mut rng = StdRng::seed_from_u64(5);
+        let outcomes = get_all_outcomes(3);
+        let mut counts = vec![("HHH", 1084)];
+        counts.extend(outcomes[1..].iter().map(|o| (o.as_str(), 988)));
+        let mut result = result_from_counts(3, &counts);
+        result.retain(|outcome, _| outcome == "HHH");
+
+        // HHH alone is far from its expected 1000 of 8000, which comparing against all 8 outcomes
+        // would hide.
+        assert!(result.monte_carlo_pvalue(200, &mut rng) < 0.05);
+    }
+
+    #[test]
+    fn test_monte_carlo_pvalue_unsampleable() {
+        let mut rng = StdRng::seed_from_u64(5);
+        let mut result = run(1, 10);
+        result.expected_by_outcome = Some(BTreeMap::from([
+            ("H".to_string(), 0.0),
+            ("T".to_string(), 0.0),
+        ]));
+
+        assert!(result.monte_carlo_pvalue(10, &mut rng).is_nan());
+    }
+
+    #[test]
+    fn test_try_run_too_large() {
+        assert_eq!(
+            try_run(63, 10).err(),
+            Some(SimError::TooLarge {
+                flips_per_iteration: 63
+            })
+        );
+        assert_eq!(
+            try_run(24, 10).err(),
+            Some(SimError::TooLarge {
+                flips_per_iteration: 24
+            })
+        );
+        assert!(try_run(10, 10).is_ok());
+
+        assert!(estimate(24, 10).exceeds_limit);
+        assert!(!estimate(23, 10).exceeds_limit);
+        assert!(estimate(usize::MAX, 10).exceeds_limit);
+        assert_eq!(
+            run_without_replacement(30, 30, 1).err(),
+            Some(SimError::TooLarge {
+                flips_per_iteration: 60
+            })
+        );
+    }
+
+    #[test]
+    fn test_set_expected_map_invalid() {
+        let mut result = result_from_counts(1, &[("H", 60), ("T", 40)]);
+        let map = |entries: &[(&str, f64)]| -> BTreeMap<String, f64> {
+            entries
+                .iter()
+                .map(|(outcome, p)| (outcome.to_string(), *p))
+                .collect()
+        };
+
+        assert_eq!(
+            result.set_expected_map(map(&[("H", 0.5), ("T", 0.25), ("X", 0.25)])),
+            Err(SimError::InvalidOutcome("X".to_string()))
+        );
+        assert_eq!(
+            result.set_expected_map(map(&[("H", 1.0)])),
+            Err(SimError::MissingExpected("T".to_string()))
+        );
+        assert_eq!(
+            result.set_expected_map(map(&[("H", 1.0), ("T", 0.0)])),
+            Err(SimError::InvalidProbability {
+                outcome: "T".to_string(),
+                probability: 0.0
+            })
+        );
+        assert!(matches!(
+            result.set_expected_map(map(&[("H", f64::NAN), ("T", 0.5)])),
+            Err(SimError::InvalidProbability { .. })
+        ));
+        assert_eq!(
+            result.set_expected_map(map(&[("H", 0.6), ("T", 0.6)])),
+            Err(SimError::ProbabilitySum(1.2))
+        );
+
+        assert_eq!(result.comparison()[0].expected, 0.5);
+        assert_eq!(
+            result.set_expected_map(map(&[("H", 0.6), ("T", 0.4)])),
+            Ok(())
+        );
+        assert_eq!(result.chi_square(), 0.0);
+        assert_eq!(result.expected_float_count(), 50.0);
+    }
+
+    #[cfg(feature = "test-utils")]
+    #[test]
+    fn test_assert_fair_passes() {
+        assert_fair(2, 10000, 0.25);
+    }
+
+    #[cfg(feature = "test-utils")]
+    #[test]
+    #[should_panic(expected = "deviates from expected")]
+    fn test_assert_fair_panics() {
+        assert_fair(3, 10, 0.0);
+    }
+
+    #[cfg(feature = "log")]
+    #[test]
+    fn test_run_logs_progress() {
+        use std::sync::Mutex;
+        use std::thread::{self, ThreadId};
+
+        // The logger is process-wide, so it records which thread each message came from to leave
+        // out messages from other tests running `run` in parallel.
+        struct CapturingLogger {
+            messages: Mutex<Vec<(ThreadId, log::Level, String)>>,
+        }
+
+        impl log::Log for CapturingLogger {
+            fn enabled(&self, _: &log::Metadata) -> bool {
+                true
+            }
+
+            fn log(&self, record: &log::Record) {
+                self.messages.lock().unwrap().push((
+                    thread::current().id(),
+                    record.level(),
+                    record.args().to_string(),
+                ));
+            }
+
+            fn flush(&self) {}
+        }
+
+        static LOGGER: CapturingLogger = CapturingLogger {
+            messages: Mutex::new(Vec::new()),
+        };
+
+        log::set_logger(&LOGGER).unwrap();
+        log::set_max_level(log::LevelFilter::Trace);
+
+        run(2, 100);
+
+        let messages = LOGGER.messages.lock().unwrap();
+        let this_thread = thread::current().id();
+        let messages_at = |level| -> Vec<&String> {
+            messages
+                .iter()
+                .filter(|(thread, l, _)| *thread == this_thread && *l == level)
+                .map(|(_, _, message)| message)
+                .collect()
+        };
+        let debug = messages_at(log::Level::Debug);
+        let trace = messages_at(log::Level::Trace);
+
+        assert_eq!(debug.len(), 2);
+        assert!(debug[0].contains("flips_per_iteration=2"));
+        assert!(debug[1].contains("100 iterations"));
+        assert_eq!(trace.len(), 10);
+        assert_eq!(trace[9], "completed 100/100 iterations");
+    }
+
+    #[test]
+    fn test_coin_display() {
+        let h = Coin::Heads;
+        let t = Coin::Tails;
+
+        assert_eq!(format!("{h}{t}"), "HT");
+    }
+
+    #[test]
+    fn test_coin_to_string() {
+        let h = Coin::Heads;
+
+        assert_eq!(h.to_string(), "H");
+    }
+
+    #[test]
+    fn test_coin_eq() {
         let c1 = Coin::Heads;
         let c2 = Coin::Heads;
 
@@ -281,4 +7464,3 @@ mod tests {
         assert!(c1 != c2);
     }
 }
-
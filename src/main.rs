@@ -4,56 +4,234 @@
 //! # Example Usage
 //!
 //! coin_flip_simulation 3 8000
+//!
+//! An optional third argument seeds the simulation so it's reproducible:
+//!
+//! coin_flip_simulation 3 8000 42
+//!
+//! # Environment
+//!
+//! When the iterations or seed aren't given as arguments, they're read from the
+//! `COIN_FLIP_ITERATIONS` and `COIN_FLIP_SEED` environment variables. Arguments always take
+//! precedence over the environment, and the seed is optional in both.
+//!
+//! # Watch Mode
+//!
+//! `--watch` followed by an interval in milliseconds re-runs the simulation at that interval,
+//! clearing the terminal and redrawing the results each time until interrupted with Ctrl-C:
+//!
+//! coin_flip_simulation 3 8000 --watch 1000
+//!
+//! With a seed, each re-run uses the next seed along so the redraws differ but stay reproducible.
 
-use std::{env, process};
+use std::io::{self, Write};
+use std::time::Duration;
+use std::{env, process, thread};
 
-use coin_flip_simulation;
+const USAGE: &str =
+    "usage: coin_flip_simulation flips_per_iteration [iterations] [seed] [--watch milliseconds]";
 
 fn main() {
-    let (flips_per_iteration, iterations) = parse_args();
+    let (flips_per_iteration, iterations, seed, watch) = parse_args();
 
-    let result = coin_flip_simulation::run(flips_per_iteration, iterations); 
-    println!("{result}");
+    if coin_flip_simulation::estimate(flips_per_iteration, iterations).exceeds_limit {
+        eprintln!(
+            "error: results for {flips_per_iteration} flips per iteration would take more than \
+             {} bytes",
+            coin_flip_simulation::MAX_RESULT_BYTES,
+        );
+        process::exit(1);
+    }
+
+    let Some(interval) = watch else {
+        report(flips_per_iteration, iterations, seed);
+        return;
+    };
+
+    // Nothing about the terminal is changed beyond what's printed, so the default Ctrl-C handling
+    // of ending the process leaves it as it was.
+    for round in 0u64.. {
+        print!("\x1b[2J\x1b[H");
+        report(
+            flips_per_iteration,
+            iterations,
+            seed.map(|seed| seed.wrapping_add(round)),
+        );
+        let _ = io::stdout().flush();
+
+        thread::sleep(interval);
+    }
 }
 
-fn parse_args() -> (usize, usize) {
-    let mut args = env::args();
-    args.next();
- 
-    let on_err = || {
-        eprintln!("usage: coin_flip_simulation flips_per_iteration iterations");
-        process::exit(1);
+/// Runs the simulation and prints its results, along with a warning if there were too few
+/// iterations.
+fn report(flips_per_iteration: usize, iterations: usize, seed: Option<u64>) {
+    let result = match seed {
+        Some(seed) => coin_flip_simulation::run_seeded(flips_per_iteration, iterations, seed),
+        None => coin_flip_simulation::run(flips_per_iteration, iterations),
     };
-    
-    let flips_per_iteration = args.next()
-        .unwrap_or_else(on_err);
-    
-    let flips_per_iteration: Result<usize, _> = flips_per_iteration
-        .trim()
-        .parse();
+    println!("{result}");
+
+    if let Some(warning) = result.sample_size_warning() {
+        eprintln!("warning: {warning}");
+    }
+}
+
+fn parse_args() -> (usize, usize, Option<u64>, Option<Duration>) {
+    let args: Vec<String> = env::args().skip(1).collect();
 
-    let flips_per_iteration = match flips_per_iteration {
-        Ok(num) => num,
-        Err(_) => {
-            on_err();
-            0
+    split_watch(&args)
+        .and_then(|(args, watch)| {
+            let (flips_per_iteration, iterations, seed) = resolve_args(
+                &args,
+                env::var("COIN_FLIP_ITERATIONS").ok(),
+                env::var("COIN_FLIP_SEED").ok(),
+            )?;
+            Some((flips_per_iteration, iterations, seed, watch))
+        })
+        .unwrap_or_else(|| {
+            eprintln!("{USAGE}");
+            process::exit(1);
+        })
+}
+
+/// Separates a `--watch` flag and its interval in milliseconds from the other arguments.
+///
+/// Returns `None` if the flag is repeated, or if its interval is missing, can't be parsed or is
+/// zero.
+fn split_watch(args: &[String]) -> Option<(Vec<String>, Option<Duration>)> {
+    let mut rest = Vec::with_capacity(args.len());
+    let mut watch = None;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if arg != "--watch" {
+            rest.push(arg.clone());
+            continue;
         }
-    };
 
-    let iterations = args.next()
-        .unwrap_or_else(on_err);
-    
-    let iterations: Result<usize, _> = iterations
+        let millis: u64 = args.next()?.trim().parse().ok()?;
+        if watch.is_some() || millis == 0 {
+            return None;
+        }
+        watch = Some(Duration::from_millis(millis));
+    }
+
+    Some((rest, watch))
+}
+
+/// Resolves the flips per iteration, iterations and seed from the command line arguments, falling
+/// back to the given environment values for any that are missing.
+///
+/// Returns `None` if the flips per iteration or iterations are missing, or if any value that's
+/// present can't be parsed.
+fn resolve_args(
+    args: &[String],
+    env_iterations: Option<String>,
+    env_seed: Option<String>,
+) -> Option<(usize, usize, Option<u64>)> {
+    if args.len() > 3 {
+        return None;
+    }
+
+    let flips_per_iteration = args.first()?.trim().parse().ok()?;
+
+    let iterations = args
+        .get(1)
+        .cloned()
+        .or(env_iterations)?
         .trim()
-        .parse();
-
-    let iterations = match iterations {
-        Ok(num) => num,
-        Err(_) => {
-            on_err();
-            0
-        }, 
+        .parse()
+        .ok()?;
+
+    let seed = match args.get(2).cloned().or(env_seed) {
+        Some(seed) => Some(seed.trim().parse().ok()?),
+        None => None,
     };
 
-    (flips_per_iteration, iterations)
+    Some((flips_per_iteration, iterations, seed))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(values: &[&str]) -> Vec<String> {
+        values.iter().map(|v| v.to_string()).collect()
+    }
+
+    #[test]
+    fn test_resolve_args_cli_only() {
+        assert_eq!(
+            resolve_args(&args(&["3", "8000"]), None, None),
+            Some((3, 8000, None))
+        );
+        assert_eq!(
+            resolve_args(&args(&["3", "8000", "42"]), None, None),
+            Some((3, 8000, Some(42)))
+        );
+    }
+
+    #[test]
+    fn test_resolve_args_env_fallback() {
+        let env_iterations = Some("500".to_string());
+        let env_seed = Some("7".to_string());
+
+        assert_eq!(
+            resolve_args(&args(&["3"]), env_iterations.clone(), env_seed.clone()),
+            Some((3, 500, Some(7)))
+        );
+        assert_eq!(
+            resolve_args(&args(&["3", "8000", "42"]), env_iterations, env_seed),
+            Some((3, 8000, Some(42)))
+        );
+    }
+
+    #[test]
+    fn test_resolve_args_invalid() {
+        assert_eq!(
+            resolve_args(&args(&[]), Some("500".to_string()), None),
+            None
+        );
+        assert_eq!(resolve_args(&args(&["3"]), None, None), None);
+        assert_eq!(
+            resolve_args(&args(&["3"]), Some("lots".to_string()), None),
+            None
+        );
+        assert_eq!(
+            resolve_args(&args(&["3", "10"]), None, Some("x".to_string())),
+            None
+        );
+        assert_eq!(
+            resolve_args(&args(&["3", "10", "1", "2"]), None, None),
+            None
+        );
+    }
+
+    #[test]
+    fn test_split_watch() {
+        assert_eq!(
+            split_watch(&args(&["3", "8000", "--watch", "1000"])),
+            Some((args(&["3", "8000"]), Some(Duration::from_millis(1000))))
+        );
+        assert_eq!(
+            split_watch(&args(&["--watch", "250", "3"])),
+            Some((args(&["3"]), Some(Duration::from_millis(250))))
+        );
+        assert_eq!(
+            split_watch(&args(&["3", "8000"])),
+            Some((args(&["3", "8000"]), None))
+        );
+    }
+
+    #[test]
+    fn test_split_watch_invalid() {
+        assert_eq!(split_watch(&args(&["3", "8000", "--watch"])), None);
+        assert_eq!(split_watch(&args(&["3", "8000", "--watch", "soon"])), None);
+        assert_eq!(split_watch(&args(&["3", "8000", "--watch", "0"])), None);
+        assert_eq!(
+            split_watch(&args(&["3", "--watch", "10", "--watch", "20"])),
+            None
+        );
+    }
 }